@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, Game};
+use crate::gamephase::PlayerId;
+use crate::pregame::{FactionSelector, PreGame};
+use crate::Result;
+
+/// Everything that decides a game before the first action: the seed of the setup and
+/// the factions selected from the pool, in player order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSetup {
+    pub seed: u64,
+    pub num_players: u32,
+    pub factions: Vec<usize>, // Indices in the faction pool, indexed by player ID
+}
+
+impl GameSetup {
+    /// Sets up the game as it was before the first action.
+    pub fn start(&self) -> Result<Game> {
+        let pregame = PreGame::new_seeded(self.num_players, self.seed);
+        let mut selector = FactionSelector::new(&pregame);
+        for &idx in &self.factions {
+            selector.select(idx)?;
+        }
+
+        Ok(Game::new(pregame.finish(&selector)?))
+    }
+}
+
+#[cfg(test)]
+impl GameSetup {
+    /// A fixed setup where every player selects the faction of the pool with their own
+    /// index.
+    pub(crate) fn for_test(num_players: u32) -> Self {
+        GameSetup {
+            seed: 5,
+            num_players,
+            factions: (0..num_players as usize).collect(),
+        }
+    }
+}
+
+/// The game of `GameSetup::for_test` before the first action.
+#[cfg(test)]
+pub(crate) fn test_game(num_players: u32) -> Game {
+    GameSetup::for_test(num_players).start().unwrap()
+}
+
+/// The next initial workshop to place, on the first free hex of the player's color.
+#[cfg(test)]
+pub(crate) fn next_placement(game: &Game) -> Option<(PlayerId, Action)> {
+    let Game::BuildingPlacement(placer) = game else {
+        return None;
+    };
+    let player_id = placer.player_to_move().ok()?;
+    let pos = crate::map::all_positions().find(|&pos| placer.valid_placement(player_id, pos))?;
+
+    Some((player_id, Action::Place { pos }))
+}
+
+/// Places every initial workshop, see `next_placement`, which leaves the game at the
+/// first action of the first round.
+#[cfg(test)]
+pub(crate) fn place_initial_buildings(game: &mut Game) {
+    while let Some((player_id, action)) = next_placement(game) {
+        game.apply(player_id, &action).unwrap();
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub player_id: PlayerId,
+    pub action: Action,
+}
+
+/// The setup of a game and every action accepted since, in order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionLog {
+    setup: GameSetup,
+    entries: Vec<LogEntry>,
+}
+
+impl ActionLog {
+    pub fn new(setup: GameSetup) -> Self {
+        ActionLog {
+            setup,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn setup(&self) -> &GameSetup {
+        &self.setup
+    }
+
+    pub fn entries(&self) -> &Vec<LogEntry> {
+        &self.entries
+    }
+
+    pub fn push(&mut self, player_id: PlayerId, action: Action) {
+        self.entries.push(LogEntry { player_id, action });
+    }
+
+    /// Rebuilds the game as it was after the first `num_entries` actions.
+    pub fn replay(&self, num_entries: usize) -> Result<Game> {
+        let mut game = self.setup.start()?;
+        for entry in self.entries.iter().take(num_entries) {
+            game.apply(entry.player_id, &entry.action)?;
+        }
+
+        Ok(game)
+    }
+}
+
+/// A game that records every accepted action.
+pub struct LoggedGame {
+    game: Game,
+    log: ActionLog,
+}
+
+impl LoggedGame {
+    pub fn new(setup: GameSetup) -> Result<Self> {
+        Ok(LoggedGame {
+            game: setup.start()?,
+            log: ActionLog::new(setup),
+        })
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn log(&self) -> &ActionLog {
+        &self.log
+    }
+
+    /// Applies the action and appends it to the log. Rejected actions are not logged.
+    pub fn apply(&mut self, player_id: PlayerId, action: Action) -> Result<()> {
+        self.game.apply(player_id, &action)?;
+        self.log.push(player_id, action);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map;
+
+    use parameterized::parameterized;
+
+    fn place_all(game: &mut LoggedGame) {
+        while let Some((player_id, action)) = next_placement(game.game()) {
+            game.apply(player_id, action).unwrap();
+        }
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn replay_rebuilds_every_state(num_players: u32) {
+        let mut game = LoggedGame::new(GameSetup::for_test(num_players)).unwrap();
+        let mut states = vec![serde_json::to_value(game.game()).unwrap()];
+
+        place_all(&mut game);
+        for _ in 0..num_players {
+            let player_id = game.game().player_to_move().unwrap();
+            let pass = Action::Pass {
+                bonus_tile: game.game().state().bonus_tiles().first().copied(),
+            };
+            game.apply(player_id, pass).unwrap();
+            states.push(serde_json::to_value(game.game()).unwrap());
+        }
+
+        let log = game.log();
+        let num_placements = 2 * num_players as usize;
+        assert_eq!(log.entries().len(), num_placements + num_players as usize);
+        assert_eq!(
+            serde_json::to_value(log.replay(0).unwrap()).unwrap(),
+            states[0]
+        );
+        for (i, state) in states.iter().enumerate().skip(1) {
+            let replayed = log.replay(num_placements + i).unwrap();
+            assert_eq!(serde_json::to_value(replayed).unwrap(), *state);
+        }
+    }
+
+    #[test]
+    fn rejected_actions_are_not_logged() {
+        let mut game = LoggedGame::new(GameSetup::for_test(2)).unwrap();
+
+        assert!(game.apply(1, Action::Place { pos: (0, 0) }).is_err());
+
+        assert!(game.log().entries().is_empty());
+    }
+
+    #[test]
+    fn log_survives_serialization() {
+        let mut game = LoggedGame::new(GameSetup::for_test(2)).unwrap();
+        place_all(&mut game);
+
+        let json = serde_json::to_string(game.log()).unwrap();
+        let log: ActionLog = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(log, *game.log());
+        let replayed = log.replay(log.entries().len()).unwrap();
+        let map = replayed.state().map();
+        let num_buildings = map::all_positions()
+            .filter(|&(r, c)| map[r][c].building.is_some())
+            .count();
+        assert_eq!(num_buildings, 4);
+    }
+}
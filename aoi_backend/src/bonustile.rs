@@ -1,6 +1,9 @@
 use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq, Sequence)]
+use crate::resources::{Coins, Power, Resources, Scholars, Tools};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Sequence, Serialize, Deserialize)]
 pub enum BonusTile {
     BonSailing,
     BonScholar,
@@ -13,3 +16,21 @@ pub enum BonusTile {
     BonCoinsAndPower,
     BonCoins,
 }
+
+impl BonusTile {
+    /// Resources gained in the income phase while holding the tile.
+    pub fn income(&self) -> Resources {
+        match self {
+            BonusTile::BonSailing => Resources::from(Coins(2)),
+            BonusTile::BonScholar => Resources::from(Scholars(1)),
+            BonusTile::BonGuild => Resources::from(Tools(1)),
+            BonusTile::BonBigBuilding => Resources::from(Tools(2)),
+            BonusTile::BonSpade => Resources::from(Coins(2)),
+            BonusTile::BonBridge => Resources::from(Coins(3)),
+            BonusTile::BonDiscStep => Resources::from(Power(3)),
+            BonusTile::BonSchool => Resources::from(Tools(1)) + &Resources::from(Power(3)),
+            BonusTile::BonCoinsAndPower => Resources::from(Coins(2)) + &Resources::from(Power(3)),
+            BonusTile::BonCoins => Resources::from(Coins(6)),
+        }
+    }
+}
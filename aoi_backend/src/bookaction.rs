@@ -1,15 +1,27 @@
 use itertools::Itertools;
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
 
-use crate::resources::Books;
+use crate::common::Discipline;
+use crate::error::AoiError;
+use crate::resources::{Books, Coins, Resources};
+use crate::Result;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+// What the effects give
+pub const POWER_GAINED: u32 = 5;
+pub const DISC_STEPS: u32 = 2;
+pub const COINS_GAINED: Coins = Coins(6);
+pub const VP_PER_GUILD: u32 = 2;
+pub const SPADES: u32 = 3; // All on the same hex
+
+/// An action paid with books, which each player may take once per round.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct BookAction {
     cost: Books,
     effect: BookActionEffect,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
 pub enum BookActionEffect {
     GainPower,
     DiscStep,
@@ -19,6 +31,38 @@ pub enum BookActionEffect {
     Spades,
 }
 
+impl BookAction {
+    pub fn new(cost: Books, effect: BookActionEffect) -> Self {
+        BookAction { cost, effect }
+    }
+
+    /// Number of books of any colors needed to take the action.
+    pub fn cost(&self) -> Books {
+        self.cost
+    }
+
+    pub fn effect(&self) -> BookActionEffect {
+        self.effect
+    }
+
+    /// Turns the colors a player picked to pay with into the cost to pay, as long as they
+    /// add up to the cost of the action.
+    pub fn payment(&self, colors: &[(Discipline, Books)]) -> Result<Resources> {
+        let payment = colors.iter().fold(Resources::none(), |acc, &color| {
+            acc + &Resources::from(color)
+        });
+
+        if payment.total_books() != self.cost {
+            return Err(AoiError::WrongBookPayment {
+                required: self.cost,
+                paid: payment.total_books(),
+            });
+        }
+
+        Ok(payment)
+    }
+}
+
 fn all_book_actions() -> Vec<BookAction> {
     vec![
         BookAction {
@@ -49,9 +93,13 @@ fn all_book_actions() -> Vec<BookAction> {
 }
 
 pub fn new_game_random_book_actions() -> Vec<BookAction> {
-    let mut rng = rand::thread_rng();
+    new_game_book_actions_from_rng(&mut rand::thread_rng())
+}
+
+/// Picks the book actions of a game using the given random number generator.
+pub fn new_game_book_actions_from_rng<R: Rng + ?Sized>(rng: &mut R) -> Vec<BookAction> {
     let mut actions = all_book_actions();
-    actions.shuffle(&mut rng);
+    actions.shuffle(rng);
 
     actions.into_iter().take(3).collect_vec()
 }
@@ -62,6 +110,12 @@ mod tests {
 
     use super::*;
 
+    use crate::common::Color;
+    use crate::faction::Faction;
+    use crate::race::Race;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
     #[test]
     fn book_actions_no_duplicates() {
         for _ in 0..100 {
@@ -69,4 +123,69 @@ mod tests {
             assert!(!contains_duplicates(&new_game_random_book_actions()))
         }
     }
+
+    #[test]
+    fn same_seed_gives_same_book_actions() {
+        let a = new_game_book_actions_from_rng(&mut StdRng::seed_from_u64(7));
+        let b = new_game_book_actions_from_rng(&mut StdRng::seed_from_u64(7));
+
+        assert!(a == b);
+    }
+
+    #[test]
+    fn books_are_paid_in_picked_colors() {
+        let action = BookAction {
+            cost: Books(2),
+            effect: BookActionEffect::GainCoins,
+        };
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.gain(
+            &(Resources::from((Discipline::Law, Books(1)))
+                + &Resources::from((Discipline::Medicine, Books(2)))),
+        );
+
+        let payment = action
+            .payment(&[
+                (Discipline::Law, Books(1)),
+                (Discipline::Medicine, Books(1)),
+            ])
+            .unwrap();
+        faction.pay(&payment).unwrap();
+
+        assert_eq!(faction.books(Discipline::Law), Books(0));
+        assert_eq!(faction.books(Discipline::Medicine), Books(1));
+    }
+
+    #[test]
+    fn payment_must_match_cost() {
+        let action = BookAction {
+            cost: Books(2),
+            effect: BookActionEffect::GainCoins,
+        };
+
+        assert_eq!(
+            action.payment(&[(Discipline::Banking, Books(1))]),
+            Err(AoiError::WrongBookPayment {
+                required: Books(2),
+                paid: Books(1),
+            })
+        );
+    }
+
+    #[test]
+    fn books_of_missing_color_are_not_paid() {
+        let action = BookAction {
+            cost: Books(1),
+            effect: BookActionEffect::GainPower,
+        };
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.gain(&Resources::from((Discipline::Law, Books(1))));
+
+        let payment = action
+            .payment(&[(Discipline::Engineering, Books(1))])
+            .unwrap();
+
+        assert!(faction.pay(&payment).is_err());
+        assert_eq!(faction.books(Discipline::Law), Books(1));
+    }
 }
@@ -0,0 +1,41 @@
+pub mod greedy;
+pub mod mcts;
+pub mod random;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, Game};
+
+use greedy::GreedyBot;
+use mcts::{Budget, MctsBot};
+use random::RandomBot;
+
+/// A computer player, choosing actions for whichever player the game is waiting for.
+pub trait Bot {
+    /// Chooses a legal action of the player to act, or none if the game is over.
+    fn choose(&mut self, game: &Game) -> Option<Action>;
+}
+
+/// The bots available for filling seats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotKind {
+    Random,
+    Greedy,
+    Mcts,
+}
+
+/// Thinking budget of MCTS bots where none is configured. A two-player game of MCTS bots
+/// takes around ten seconds with it in a release build.
+pub const DEFAULT_MCTS_BUDGET: Budget = Budget::Iterations(200);
+
+impl BotKind {
+    /// Creates a bot of this kind. Bots making random choices draw them from `seed`, and
+    /// MCTS bots think for `mcts_budget` before every action.
+    pub fn create(&self, seed: u64, mcts_budget: Budget) -> Box<dyn Bot + Send> {
+        match self {
+            BotKind::Random => Box::new(RandomBot::new(seed)),
+            BotKind::Greedy => Box::new(GreedyBot::new()),
+            BotKind::Mcts => Box::new(MctsBot::new(seed, mcts_budget)),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::bot::Bot;
+use crate::faction::Faction;
+use crate::game::{Action, Game};
+use crate::gamephase::{GameState, PlayerId, NUM_ROUNDS};
+use crate::movegen;
+use crate::resources::{Coins, Power, Resources, Scholars, Tools};
+
+// Values in quarters of a coin. Three coins are worth one VP at the end of the game and
+// power is worth less the further it is from being spent.
+const VP_VALUE: i64 = 12;
+const COIN_VALUE: i64 = 4;
+const TOOL_VALUE: i64 = 12;
+const SCHOLAR_VALUE: i64 = 16;
+const BOOK_VALUE: i64 = 16;
+const POWER_VALUES: [i64; 3] = [1, 3, 5]; // Indexed by bowl
+
+/// Takes the action leading to the most valuable position for the player, counting VP,
+/// resources and the building income of the remaining rounds. Looks one action ahead only.
+#[derive(Default)]
+pub struct GreedyBot;
+
+impl GreedyBot {
+    pub fn new() -> Self {
+        GreedyBot
+    }
+}
+
+impl Bot for GreedyBot {
+    fn choose(&mut self, game: &Game) -> Option<Action> {
+        let player_id = movegen::player_to_act(game)?;
+
+        let mut best: Option<(i64, Action)> = None;
+        for (action, after) in movegen::legal_successors(game) {
+            let value = evaluate(after.state(), player_id);
+
+            // The first of equally good actions is kept
+            if best
+                .as_ref()
+                .is_none_or(|(best_value, _)| value > *best_value)
+            {
+                best = Some((value, action));
+            }
+        }
+
+        best.map(|(_, action)| action)
+    }
+}
+
+/// How well off the player is, in quarters of a coin.
+pub fn evaluate(state: &GameState, player_id: PlayerId) -> i64 {
+    let faction = &state.factions()[player_id];
+    let rounds_left = NUM_ROUNDS.saturating_sub(state.round()) as i64;
+    let power = (1..=3)
+        .map(|bowl| faction.power().amount(bowl) as i64 * POWER_VALUES[bowl - 1])
+        .sum::<i64>();
+
+    // The bonus tile is returned when passing, so its income is only collected once
+    let bonus_income = faction
+        .bonus_tile()
+        .map_or(0, |tile| resource_value(&tile.income()));
+
+    faction.vp().0 as i64 * VP_VALUE
+        + resource_value(&held(faction))
+        + power
+        + rounds_left * resource_value(&faction.supply().income())
+        + bonus_income
+}
+
+// Held resources apart from power, which is valued by bowl
+fn held(faction: &Faction) -> Resources {
+    let resources = faction.resources();
+
+    resources - &Resources::from(resources.get::<Power>())
+}
+
+fn resource_value(resources: &Resources) -> i64 {
+    resources.get::<Coins>().0 as i64 * COIN_VALUE
+        + resources.get::<Tools>().0 as i64 * TOOL_VALUE
+        + resources.get::<Scholars>().0 as i64 * SCHOLAR_VALUE
+        + resources.total_books().0 as i64 * BOOK_VALUE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actionlog;
+
+    use parameterized::parameterized;
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn greedy_bots_finish_the_game(num_players: u32) {
+        let mut game = actionlog::test_game(num_players);
+        let mut bot = GreedyBot::new();
+
+        while let Some(action) = bot.choose(&game) {
+            let player_id = movegen::player_to_act(&game).unwrap();
+            game.apply(player_id, &action).unwrap();
+        }
+
+        assert!(game.is_finished());
+    }
+
+    #[test]
+    fn greedy_builds_instead_of_passing() {
+        let mut game = actionlog::test_game(2);
+        actionlog::place_initial_buildings(&mut game);
+
+        let action = GreedyBot::new().choose(&game).unwrap();
+
+        assert!(matches!(
+            action,
+            Action::Build { .. } | Action::Upgrade { .. }
+        ));
+    }
+
+    #[test]
+    fn income_counts_for_the_remaining_rounds() {
+        let game = actionlog::test_game(2);
+        let state = game.state();
+        let faction = &state.factions()[0];
+
+        let income = resource_value(&faction.supply().income());
+
+        assert!(income > 0);
+        assert_eq!(
+            evaluate(state, 0),
+            faction.vp().0 as i64 * VP_VALUE
+                + resource_value(&held(faction))
+                + (1..=3)
+                    .map(|bowl| faction.power().amount(bowl) as i64 * POWER_VALUES[bowl - 1])
+                    .sum::<i64>()
+                + NUM_ROUNDS as i64 * income
+                + resource_value(&faction.bonus_tile().unwrap().income())
+        );
+    }
+}
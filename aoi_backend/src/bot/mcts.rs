@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::bot::greedy;
+use crate::bot::Bot;
+use crate::game::{Action, Game};
+use crate::gamephase::PlayerId;
+use crate::movegen;
+
+// Balances exploring rarely visited actions against exploiting the best ones
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+// Playouts are cut off after this many actions and scored by the position reached
+const PLAYOUT_DEPTH: usize = 40;
+
+/// How long the bot thinks before every action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    Iterations(u32),
+    Time(Duration),
+}
+
+/// Monte Carlo tree search over the legal actions, with random playouts.
+///
+/// Every node keeps the rewards of all players and is chosen by whoever acts there, so
+/// power offers decided by other players out of turn are searched like any other action.
+///
+/// The randomness of the setup needs no sampling. The seed draws the faction pool, the
+/// bonus tiles, the book actions and the scoring tiles of all six rounds, and all of them
+/// are laid out face up in the `GameState` before the first action. Nothing is drawn
+/// after that, so the searched game is the real one.
+pub struct MctsBot {
+    rng: StdRng,
+    budget: Budget,
+}
+
+struct Node {
+    player_id: Option<PlayerId>, // Player to act, none once the game is over
+    untried: Vec<Action>,
+    children: Vec<(Action, usize)>, // Indices in the tree
+    visits: u32,
+    rewards: Vec<f64>, // Summed over all visits, indexed by player ID
+}
+
+impl MctsBot {
+    pub fn new(seed: u64, budget: Budget) -> Self {
+        MctsBot {
+            rng: StdRng::seed_from_u64(seed),
+            budget,
+        }
+    }
+
+    fn new_node(&mut self, game: &Game) -> Node {
+        let mut untried = movegen::legal_actions(game);
+        untried.shuffle(&mut self.rng);
+
+        Node {
+            player_id: movegen::player_to_act(game),
+            untried,
+            children: Vec::new(),
+            visits: 0,
+            rewards: vec![0.0; game.state().factions().len()],
+        }
+    }
+
+    // Runs one iteration from the root, i.e. the first node of the tree
+    fn iterate(&mut self, tree: &mut Vec<Node>, root: &Game) {
+        let mut game = root.clone();
+        let mut path = vec![0];
+
+        // Selection
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            let (action, child) = select_child(tree, node);
+            game.apply(tree[node].player_id.unwrap(), &action).unwrap();
+            node = child;
+            path.push(node);
+        }
+
+        // Expansion
+        if let Some(action) = tree[node].untried.pop() {
+            game.apply(tree[node].player_id.unwrap(), &action).unwrap();
+            let child = tree.len();
+            let new_node = self.new_node(&game);
+            tree.push(new_node);
+            tree[node].children.push((action, child));
+            path.push(child);
+        }
+
+        // Playout
+        for _ in 0..PLAYOUT_DEPTH {
+            if movegen::apply_random(&mut game, &mut self.rng).is_none() {
+                break;
+            }
+        }
+
+        // Backpropagation
+        let rewards = rewards(&game);
+        for node in path {
+            tree[node].visits += 1;
+            for (total, reward) in tree[node].rewards.iter_mut().zip(&rewards) {
+                *total += reward;
+            }
+        }
+    }
+}
+
+impl Bot for MctsBot {
+    fn choose(&mut self, game: &Game) -> Option<Action> {
+        let root = self.new_node(game);
+        match root.untried.len() {
+            0 => return None,
+            1 => return root.untried.first().cloned(), // Nothing to think about
+            _ => {}
+        }
+
+        let mut tree = vec![root];
+        let start = Instant::now();
+        let mut iterations = 0;
+        while match self.budget {
+            Budget::Iterations(max) => iterations < max,
+            Budget::Time(max) => start.elapsed() < max,
+        } {
+            self.iterate(&mut tree, game);
+            iterations += 1;
+        }
+
+        // The most visited action is the most robust choice
+        tree[0]
+            .children
+            .iter()
+            .max_by_key(|(_, child)| tree[*child].visits)
+            .map(|(action, _)| action.clone())
+            .or_else(|| tree[0].untried.pop())
+    }
+}
+
+// The child with the highest upper confidence bound for the player to act
+fn select_child(tree: &[Node], node: usize) -> (Action, usize) {
+    let parent = &tree[node];
+    let player_id = parent.player_id.unwrap();
+    let log_visits = (parent.visits as f64).ln();
+
+    let ucb = |child: usize| {
+        let child = &tree[child];
+        let visits = child.visits as f64;
+
+        child.rewards[player_id] / visits + EXPLORATION * (log_visits / visits).sqrt()
+    };
+
+    parent
+        .children
+        .iter()
+        .max_by(|(_, a), (_, b)| ucb(*a).total_cmp(&ucb(*b)))
+        .cloned()
+        .unwrap()
+}
+
+// One for the player in the lead, shared if tied, and zero for the others
+fn rewards(game: &Game) -> Vec<f64> {
+    let num_players = game.state().factions().len();
+    let values = (0..num_players)
+        .map(|player_id| greedy::evaluate(game.state(), player_id))
+        .collect::<Vec<_>>();
+    let best = values.iter().copied().max().unwrap_or(0);
+    let num_best = values.iter().filter(|&&v| v == best).count() as f64;
+
+    values
+        .iter()
+        .map(|&v| if v == best { 1.0 / num_best } else { 0.0 })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actionlog;
+    use crate::common::{Color, VP};
+    use crate::faction::Faction;
+    use crate::gamephase::buildingplacement::BuildingPlacer;
+    use crate::gamephase::NUM_ROUNDS;
+    use crate::map;
+    use crate::race::Race;
+
+    use parameterized::parameterized;
+
+    // Leaves the first action to search
+    fn start_actions(num_players: u32) -> Game {
+        let mut game = actionlog::test_game(num_players);
+        actionlog::place_initial_buildings(&mut game);
+
+        game
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn setup_is_laid_out_before_the_first_action(num_players: u32) {
+        let game = actionlog::test_game(num_players);
+        let state = game.state();
+
+        assert_eq!(state.scoring_tiles().len(), NUM_ROUNDS);
+        assert!(!state.book_actions().is_empty());
+        assert!(!state.bonus_tiles().is_empty());
+        assert_eq!(state.factions().len(), num_players as usize);
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn chooses_legal_action(num_players: u32) {
+        let game = start_actions(num_players);
+        let mut bot = MctsBot::new(1, Budget::Iterations(50));
+
+        let action = bot.choose(&game).unwrap();
+
+        assert!(movegen::legal_actions(&game).contains(&action));
+    }
+
+    #[test]
+    fn same_seed_makes_same_choice() {
+        let game = start_actions(2);
+
+        let choice = |seed| MctsBot::new(seed, Budget::Iterations(30)).choose(&game);
+
+        assert_eq!(choice(4), choice(4));
+    }
+
+    #[test]
+    fn time_budget_is_kept() {
+        let game = start_actions(3);
+        let budget = Duration::from_millis(50);
+        let mut bot = MctsBot::new(1, Budget::Time(budget));
+        // The slowest of a few single iterations, each including the setup of the search
+        let iteration = (0..5)
+            .map(|seed| {
+                let start = Instant::now();
+                MctsBot::new(seed, Budget::Iterations(1)).choose(&game);
+                start.elapsed()
+            })
+            .max()
+            .unwrap();
+
+        let start = Instant::now();
+        bot.choose(&game).unwrap();
+
+        // Only the iteration running when time is up may go over, with some slack for
+        // iterations slower than the measured ones
+        assert!(start.elapsed() >= budget);
+        assert!(start.elapsed() < budget + 2 * iteration);
+    }
+
+    #[test]
+    fn nothing_to_choose_in_finished_game() {
+        let mut game = actionlog::test_game(2);
+        let mut bot = crate::bot::random::RandomBot::new(0);
+        while let Some(action) = bot.choose(&game) {
+            let player_id = movegen::player_to_act(&game).unwrap();
+            game.apply(player_id, &action).unwrap();
+        }
+
+        assert_eq!(MctsBot::new(0, Budget::Iterations(10)).choose(&game), None);
+    }
+
+    // Equal factions, apart from the VP given to each player
+    fn game_with_vp(vp: &[u32]) -> Game {
+        let factions = vp
+            .iter()
+            .map(|&vp| {
+                let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+                faction.gain_vp(VP(vp));
+                faction
+            })
+            .collect();
+
+        Game::new(BuildingPlacer::new(
+            factions,
+            map::open_map(map::MapId::Debug),
+        ))
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn leader_takes_the_reward(num_players: usize) {
+        let mut vp = vec![0; num_players];
+        vp[num_players - 1] = 10;
+
+        let rewards = rewards(&game_with_vp(&vp));
+
+        let mut expected = vec![0.0; num_players];
+        expected[num_players - 1] = 1.0;
+        assert_eq!(rewards, expected);
+    }
+
+    #[test]
+    fn tied_leaders_share_the_reward() {
+        let rewards = rewards(&game_with_vp(&[5, 0, 5]));
+
+        assert_eq!(rewards, vec![0.5, 0.0, 0.5]);
+    }
+}
@@ -0,0 +1,59 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::bot::Bot;
+use crate::game::{Action, Game};
+use crate::movegen;
+
+/// Picks uniformly among the legal actions.
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        RandomBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Bot for RandomBot {
+    fn choose(&mut self, game: &Game) -> Option<Action> {
+        movegen::apply_random(&mut game.clone(), &mut self.rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actionlog;
+
+    use parameterized::parameterized;
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn random_bots_finish_the_game(num_players: u32) {
+        let mut game = actionlog::test_game(num_players);
+        let mut bot = RandomBot::new(num_players as u64);
+
+        while let Some(action) = bot.choose(&game) {
+            let player_id = movegen::player_to_act(&game).unwrap();
+            game.apply(player_id, &action).unwrap();
+        }
+
+        assert!(game.is_finished());
+    }
+
+    #[test]
+    fn same_seed_makes_same_choices() {
+        let game = actionlog::test_game(2);
+
+        let choices = |seed| {
+            let mut bot = RandomBot::new(seed);
+            (0..10).map(|_| bot.choose(&game)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(choices(3), choices(3));
+    }
+}
@@ -1,3 +1,13 @@
+use enum_iterator::Sequence;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::common::Color;
+use crate::faction::IncomeTrack;
+use crate::resources::{Coins, Resources, Tools};
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Sequence, Serialize, Deserialize)]
 pub enum Building {
     Workshop,
     Guild,
@@ -8,3 +18,171 @@ pub enum Building {
     Tower,
     Monument,
 }
+
+/// Coins saved when building a guild next to another player's building.
+pub const GUILD_NEIGHBOR_DISCOUNT: Coins = Coins(3);
+
+impl Building {
+    /// Buildings that this building may be upgraded into.
+    pub fn upgrades(&self) -> &'static [Building] {
+        match self {
+            Building::Workshop => &[Building::Guild],
+            Building::Guild => &[Building::School, Building::Palace],
+            Building::School => &[Building::University],
+            _ => &[],
+        }
+    }
+
+    pub fn can_upgrade_to(&self, to: Building) -> bool {
+        self.upgrades().contains(&to)
+    }
+
+    /// Power offered to neighbors, and counted for their leech when adjacent to a new building.
+    pub fn power_value(&self) -> u32 {
+        match self {
+            Building::Workshop => 1,
+            Building::Guild | Building::School => 2,
+            Building::University | Building::Palace | Building::Tower => 3,
+            Building::Monument => 4,
+        }
+    }
+
+    /// Cost of building a workshop, or of upgrading into any other building. Buildings
+    /// that can't be bought have no cost.
+    pub fn cost(&self) -> Option<Resources> {
+        let (tools, coins) = match self {
+            Building::Workshop => (1, 2),
+            Building::Guild => (2, 6),
+            Building::School => (3, 5),
+            Building::University => (5, 8),
+            Building::Palace => (4, 6),
+            Building::Tower | Building::Monument => return None,
+        };
+
+        Some(Resources::from(Tools(tools)) + &Resources::from(Coins(coins)))
+    }
+}
+
+/// The buildings a faction has left to build. Every building kind has its own income
+/// track, so taking a building from the supply uncovers more income.
+#[derive(Clone)]
+pub struct BuildingSupply {
+    tracks: Vec<(Building, IncomeTrack)>, // Indexed by Building casted to usize
+}
+
+impl BuildingSupply {
+    pub fn new(color: &Color) -> Self {
+        let tracks = enum_iterator::all::<Building>()
+            .map(|b| (b, IncomeTrack::new(color, &b)))
+            .collect();
+
+        BuildingSupply { tracks }
+    }
+
+    pub fn remaining(&self, building: Building) -> usize {
+        self.track(building).remaining()
+    }
+
+    pub fn take(&mut self, building: Building) -> Result<()> {
+        self.track_mut(building).remove_building()
+    }
+
+    pub fn put_back(&mut self, building: Building) -> Result<()> {
+        self.track_mut(building).put_building()
+    }
+
+    pub fn income(&self) -> Resources {
+        self.tracks
+            .iter()
+            .fold(Resources::none(), |acc, (_, t)| acc + &t.income())
+    }
+
+    pub fn track(&self, building: Building) -> &IncomeTrack {
+        &self.tracks[building as usize].1
+    }
+
+    fn track_mut(&mut self, building: Building) -> &mut IncomeTrack {
+        &mut self.tracks[building as usize].1
+    }
+}
+
+impl Serialize for BuildingSupply {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.tracks.len()))?;
+        for (building, track) in &self.tracks {
+            map.serialize_entry(building, &track.remaining())?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::resources::{Scholars, Tools};
+
+    #[test]
+    fn upgrade_graph() {
+        assert!(Building::Workshop.can_upgrade_to(Building::Guild));
+        assert!(Building::Guild.can_upgrade_to(Building::School));
+        assert!(Building::Guild.can_upgrade_to(Building::Palace));
+        assert!(Building::School.can_upgrade_to(Building::University));
+
+        assert!(!Building::Workshop.can_upgrade_to(Building::School));
+        assert!(!Building::Guild.can_upgrade_to(Building::University));
+        assert!(!Building::Palace.can_upgrade_to(Building::University));
+        assert!(Building::University.upgrades().is_empty());
+    }
+
+    #[test]
+    fn full_supply_for_new_faction() {
+        let supply = BuildingSupply::new(&Color::Yellow);
+
+        assert_eq!(supply.remaining(Building::Workshop), 9);
+        assert_eq!(supply.remaining(Building::Guild), 4);
+        assert_eq!(supply.remaining(Building::School), 3);
+        assert_eq!(supply.remaining(Building::University), 1);
+        assert_eq!(supply.remaining(Building::Palace), 1);
+        assert_eq!(supply.remaining(Building::Tower), 1);
+        assert_eq!(supply.remaining(Building::Monument), 1);
+    }
+
+    #[test]
+    fn taking_buildings_increases_income() {
+        let mut supply = BuildingSupply::new(&Color::Yellow);
+        assert_eq!(supply.income(), Resources::from(Tools(1)));
+
+        supply.take(Building::Workshop).unwrap();
+        supply.take(Building::School).unwrap();
+
+        assert_eq!(supply.remaining(Building::Workshop), 8);
+        assert_eq!(
+            supply.income(),
+            Resources::from(Tools(2)) + &Resources::from(Scholars(1))
+        );
+    }
+
+    #[test]
+    fn cant_take_more_than_supply() {
+        let mut supply = BuildingSupply::new(&Color::Yellow);
+
+        supply.take(Building::University).unwrap();
+
+        assert!(supply.take(Building::University).is_err());
+    }
+
+    #[test]
+    fn cant_put_back_into_full_supply() {
+        let mut supply = BuildingSupply::new(&Color::Yellow);
+
+        assert!(supply.put_back(Building::Guild).is_err());
+
+        supply.take(Building::Guild).unwrap();
+        supply.put_back(Building::Guild).unwrap();
+        assert_eq!(supply.remaining(Building::Guild), 4);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::common::Color;
+use crate::resources::{Coins, Power, Resources, Scholars, Tools};
+
+/// What a faction gets from its home color, at setup and for the rest of the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorBonus {
+    pub disc_steps: [u32; 4],            // Indexed by Discipline
+    pub power: Option<[u32; 3]>,         // Replaces the starting power bowls
+    pub resources: Resources,            // Gained at setup
+    pub sailing_level: u32,              // Starting level
+    pub dig_upg_cost: Option<Resources>, // Replaces the cost of upgrading digging
+    pub setup_spades: u32,               // Used when placing the initial workshops
+    pub workshop_income: Resources,      // Every round, on top of the base income
+    pub palace_income: Resources,
+}
+
+impl Default for ColorBonus {
+    fn default() -> Self {
+        ColorBonus {
+            disc_steps: [0; 4],
+            power: None,
+            resources: Resources::none(),
+            sailing_level: 0,
+            dig_upg_cost: None,
+            setup_spades: 0,
+            workshop_income: Resources::none(),
+            palace_income: Resources::from(Power(4)),
+        }
+    }
+}
+
+pub fn color_bonus(color: Color) -> ColorBonus {
+    match color {
+        Color::Yellow => ColorBonus {
+            setup_spades: 1,
+            palace_income: Resources::from(Power(4)) + &Resources::from(Coins(1)),
+            ..Default::default()
+        },
+        Color::Brown => ColorBonus {
+            dig_upg_cost: Some(
+                Resources::from(Tools(1))
+                    + &Resources::from(Coins(1))
+                    + &Resources::from(Scholars(1)),
+            ),
+            palace_income: Resources::from(Power(2)) + &Resources::from(Tools(1)),
+            ..Default::default()
+        },
+        Color::Black => ColorBonus {
+            power: Some([3, 9, 0]),
+            resources: Resources::from(Scholars(1)),
+            palace_income: Resources::from(Power(2)) + &Resources::from(Scholars(1)),
+            ..Default::default()
+        },
+        Color::Blue => ColorBonus {
+            sailing_level: 1,
+            palace_income: Resources::from(Power(2)) + &Resources::from(Coins(2)),
+            ..Default::default()
+        },
+        Color::Green => ColorBonus {
+            disc_steps: [1; 4],
+            power: Some([4, 8, 0]),
+            ..Default::default()
+        },
+        Color::Gray => ColorBonus {
+            resources: Resources::from(Tools(1)),
+            workshop_income: Resources::from(Coins(2)),
+            palace_income: Resources::from(Power(3)) + &Resources::from(Tools(1)),
+            ..Default::default()
+        },
+        Color::Red => ColorBonus {
+            power: Some([5, 5, 2]),
+            palace_income: Resources::from(Power(6)),
+            ..Default::default()
+        },
+        Color::Colorless => ColorBonus::default(), // For testing
+    }
+}
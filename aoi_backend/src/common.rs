@@ -1,11 +1,11 @@
 use enum_iterator::Sequence;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct VP(pub u32);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Sequence, Serialize, Deserialize)]
 pub enum Discipline {
     Banking,
     Law,
@@ -15,7 +15,7 @@ pub enum Discipline {
 
 pub const DISCIPLINE_MAX: u32 = 12;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Sequence, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Sequence, Serialize, Deserialize)]
 pub enum Color {
     Yellow,
     Brown,
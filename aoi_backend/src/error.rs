@@ -1,16 +1,342 @@
 use std::fmt;
 
-#[derive(Debug, Clone)]
-struct Error(String);
+use serde::Serialize;
 
-impl fmt::Display for Error {
+use crate::bonustile::BonusTile;
+use crate::bookaction::BookActionEffect;
+use crate::building::Building;
+use crate::common::Color;
+use crate::game::Action;
+use crate::gamephase::PlayerId;
+use crate::map::Pos;
+use crate::resources::{Books, Resources};
+
+/// Every way an operation on the game can fail. Serializes with the variant name in the
+/// `error` field, along with the context of the failure.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "error")]
+pub enum AoiError {
+    // Turn order
+    NotPlayersTurn {
+        player_id: PlayerId,
+    },
+    AllPlayersPassed,
+    NotAllPlayersPassed,
+    MainActionAlreadyTaken {
+        player_id: PlayerId,
+    },
+    MainActionRequired {
+        player_id: PlayerId,
+    },
+    WaitingForLeech,
+    NoLeechOffer {
+        player_id: PlayerId,
+    },
+    NothingToUndo {
+        player_id: PlayerId,
+    },
+    NothingToRedo {
+        player_id: PlayerId,
+    },
+    ActionNotAllowed {
+        action: Action,
+    },
+    NoSpadesPending,
+    SpadesPending {
+        player_id: PlayerId,
+    },
+
+    // Setup
+    AllFactionsSelected,
+    FactionAlreadySelected {
+        idx: usize,
+    },
+    NoSuchFaction {
+        idx: usize,
+    },
+    FactionSelectionNotFinished,
+    AllBuildingsPlaced,
+    PlacementNotFinished,
+
+    // Map
+    OutsideMap {
+        pos: Pos,
+    },
+    HexOccupied {
+        pos: Pos,
+    },
+    WaterHex {
+        pos: Pos,
+    },
+    WrongColor {
+        pos: Pos,
+        required: Color,
+        actual: Color,
+    },
+    NotReachable {
+        pos: Pos,
+    },
+    NoOwnBuilding {
+        player_id: PlayerId,
+        pos: Pos,
+    },
+    AlreadyColor {
+        pos: Pos,
+        color: Color,
+    },
+    InvalidTerraformColor {
+        color: Color,
+    },
+    InvalidBridge {
+        ends: (Pos, Pos),
+    },
+    BridgeExists {
+        ends: (Pos, Pos),
+    },
+    NoBridgesLeft {
+        player_id: PlayerId,
+    },
+
+    // Resources
+    NotEnoughResources {
+        required: Resources,
+        available: Resources,
+    },
+    NotEnoughPower {
+        bowl: usize,
+        required: u32,
+        available: u32,
+    },
+    WrongBookPayment {
+        required: Books,
+        paid: Books,
+    },
+    MaxSailingLevel,
+    MinDiggingCost,
+
+    // Book actions
+    NoSuchBookAction {
+        idx: usize,
+    },
+    BookActionTaken {
+        idx: usize,
+    },
+    BookActionTargetRequired {
+        effect: BookActionEffect,
+    },
+
+    // Buildings
+    NoneLeftInSupply {
+        building: Building,
+    },
+    InvalidUpgrade {
+        from: Building,
+        to: Building,
+    },
+    NotPurchasable {
+        building: Building,
+    },
+    IncomeTrackEmpty,
+    IncomeTrackFull,
+
+    // Bonus tiles
+    BonusTileUnavailable {
+        tile: BonusTile,
+    },
+    BonusTileRequired,
+    NoBonusTileInLastRound,
+
+    // Malformed input
+    InvalidMap {
+        reason: String,
+    },
+    JsonDiff {
+        reason: String,
+    },
+}
+
+impl fmt::Display for AoiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        f.write_str(&self.0)
+        match self {
+            AoiError::NotPlayersTurn { player_id } => {
+                write!(f, "Not the turn of player {}", player_id)
+            }
+            AoiError::AllPlayersPassed => f.write_str("All players have passed"),
+            AoiError::NotAllPlayersPassed => f.write_str("All players have not passed"),
+            AoiError::MainActionAlreadyTaken { player_id } => write!(
+                f,
+                "Player {} has already taken the main action this turn",
+                player_id
+            ),
+            AoiError::MainActionRequired { player_id } => write!(
+                f,
+                "Player {} must take a main action before ending the turn",
+                player_id
+            ),
+            AoiError::WaitingForLeech => {
+                f.write_str("Waiting for players to decide on power offers")
+            }
+            AoiError::NoLeechOffer { player_id } => {
+                write!(f, "No power offered to player {}", player_id)
+            }
+            AoiError::NothingToUndo { player_id } => {
+                write!(f, "Player {} has nothing to undo this turn", player_id)
+            }
+            AoiError::NothingToRedo { player_id } => {
+                write!(f, "Player {} has nothing to redo", player_id)
+            }
+            AoiError::ActionNotAllowed { action } => {
+                write!(f, "{:?} is not allowed in this phase", action)
+            }
+            AoiError::NoSpadesPending => f.write_str("No player has spades left to dig"),
+            AoiError::SpadesPending { player_id } => {
+                write!(f, "Player {} still has spades to dig", player_id)
+            }
+            AoiError::AllFactionsSelected => f.write_str("All factions are already selected"),
+            AoiError::FactionAlreadySelected { idx } => {
+                write!(f, "Faction {} has already been selected", idx)
+            }
+            AoiError::NoSuchFaction { idx } => write!(f, "There is no faction {}", idx),
+            AoiError::FactionSelectionNotFinished => {
+                f.write_str("All players must have selected a faction")
+            }
+            AoiError::AllBuildingsPlaced => f.write_str("All buildings have already been placed"),
+            AoiError::PlacementNotFinished => {
+                f.write_str("All players have not placed all their buildings")
+            }
+            AoiError::OutsideMap { pos } => write!(f, "Hex {:?} is outside of the map", pos),
+            AoiError::HexOccupied { pos } => write!(f, "Hex {:?} is already occupied", pos),
+            AoiError::WaterHex { pos } => write!(f, "Hex {:?} is water", pos),
+            AoiError::WrongColor {
+                pos,
+                required,
+                actual,
+            } => write!(
+                f,
+                "Hex {:?} is {:?} but must be {:?}",
+                pos, actual, required
+            ),
+            AoiError::NotReachable { pos } => write!(f, "Hex {:?} is not reachable", pos),
+            AoiError::NoOwnBuilding { player_id, pos } => {
+                write!(f, "Player {} has no building on hex {:?}", player_id, pos)
+            }
+            AoiError::AlreadyColor { pos, color } => {
+                write!(f, "Hex {:?} is already {:?}", pos, color)
+            }
+            AoiError::InvalidBridge { ends } => {
+                write!(f, "Hexes {:?} can't be joined by a bridge", ends)
+            }
+            AoiError::BridgeExists { ends } => {
+                write!(f, "Hexes {:?} are already joined by a bridge", ends)
+            }
+            AoiError::NoBridgesLeft { player_id } => {
+                write!(f, "Player {} has no bridges left", player_id)
+            }
+            AoiError::InvalidTerraformColor { color } => {
+                write!(f, "Can't terraform into {:?}", color)
+            }
+            AoiError::NotEnoughResources {
+                required,
+                available,
+            } => write!(
+                f,
+                "Not enough resources (required {:?}, available {:?})",
+                required, available
+            ),
+            AoiError::NotEnoughPower {
+                bowl,
+                required,
+                available,
+            } => write!(
+                f,
+                "Not enough power in bowl {} (required {}, available {})",
+                bowl, required, available
+            ),
+            AoiError::WrongBookPayment { required, paid } => write!(
+                f,
+                "Wrong number of books paid (required {}, paid {})",
+                required.0, paid.0
+            ),
+            AoiError::MaxSailingLevel => f.write_str("Sailing is already at the highest level"),
+            AoiError::MinDiggingCost => f.write_str("Digging is already as cheap as it gets"),
+            AoiError::NoSuchBookAction { idx } => write!(f, "No book action {}", idx),
+            AoiError::BookActionTaken { idx } => {
+                write!(f, "Book action {} is already taken this round", idx)
+            }
+            AoiError::BookActionTargetRequired { effect } => {
+                write!(f, "Book action {:?} needs a target", effect)
+            }
+            AoiError::NoneLeftInSupply { building } => {
+                write!(f, "No {:?} left in supply", building)
+            }
+            AoiError::InvalidUpgrade { from, to } => {
+                write!(f, "{:?} can't be upgraded into {:?}", from, to)
+            }
+            AoiError::NotPurchasable { building } => {
+                write!(f, "{:?} can't be bought", building)
+            }
+            AoiError::IncomeTrackEmpty => f.write_str("No buildings left on income track"),
+            AoiError::IncomeTrackFull => f.write_str("Income track is already full"),
+            AoiError::BonusTileUnavailable { tile } => {
+                write!(f, "Bonus tile {:?} is not available", tile)
+            }
+            AoiError::BonusTileRequired => f.write_str("A bonus tile must be taken when passing"),
+            AoiError::NoBonusTileInLastRound => {
+                f.write_str("No bonus tiles are taken in the last round")
+            }
+            AoiError::InvalidMap { reason } => write!(f, "Invalid map: {}", reason),
+            AoiError::JsonDiff { reason } => write!(f, "Json diff failed: {}", reason),
+        }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for AoiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-pub fn create_error(msg: &str) -> Box<dyn std::error::Error> {
-    Box::new(Error(String::from(msg)))
+    use crate::resources::{Coins, Tools};
+
+    use serde_json::json;
+
+    #[test]
+    fn error_serializes_with_context() {
+        let err = AoiError::NotEnoughResources {
+            required: Resources::from(Tools(2)) + &Resources::from(Coins(6)),
+            available: Resources::from(Tools(1)) + &Resources::from(Coins(6)),
+        };
+
+        let json = serde_json::to_value(&err).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "error": "NotEnoughResources",
+                "required": { "tools": 2, "coins": 6 },
+                "available": { "tools": 1, "coins": 6 },
+            })
+        );
+    }
+
+    #[test]
+    fn unit_error_serializes_to_its_name() {
+        let json = serde_json::to_value(AoiError::WaitingForLeech).unwrap();
+
+        assert_eq!(json, json!({ "error": "WaitingForLeech" }));
+    }
+
+    #[test]
+    fn error_displays_context() {
+        let err = AoiError::NotEnoughPower {
+            bowl: 3,
+            required: 5,
+            available: 2,
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "Not enough power in bowl 3 (required 5, available 2)"
+        );
+    }
 }
@@ -1,24 +1,33 @@
 use std::cmp::min;
+use std::sync::Arc;
 
-use crate::building::Building;
-use crate::common::{Color, Discipline, DISCIPLINE_MAX};
+use crate::bonustile::BonusTile;
+use crate::building::{Building, BuildingSupply, GUILD_NEIGHBOR_DISCOUNT};
+use crate::colorbonus;
+use crate::common::{Color, Discipline, DISCIPLINE_MAX, VP};
 use crate::Result;
 
-use crate::error::create_error;
-use crate::power::PowerBowls;
+use crate::error::AoiError;
+use crate::power::{self, Conversion, PowerBowls};
 use crate::race::Race;
-use crate::resources::{Books, Coins, Power, Resources, Scholars, Tools};
+use crate::resources::{self, Books, Coins, Power, Resource, Resources, Scholars, Tools};
 
+use enum_iterator::all;
 use serde::Serialize;
 
-#[derive(Serialize)]
+pub const MAX_SAILING_LEVEL: u32 = 3;
+pub const MIN_DIGGING_COST: Tools = Tools(1);
+
+#[derive(Clone, Serialize)]
 pub struct Faction {
     race: Race,
     color: Color,
+    vp: VP,
     digging_cost: Tools,
     sailing_level: u32,
     tools: Tools,
     coins: Coins,
+    #[serde(serialize_with = "resources::serialize_books")]
     books: [Books; 4], // One book for each discipline, indexed by Discipline casted to usize
     scholars: Scholars,
     scholars_cap: Scholars,
@@ -26,6 +35,9 @@ pub struct Faction {
     power: PowerBowls,
     dig_upg_cost: Resources,
     sailing_upg_cost: Resources,
+    bonus_tile: Option<BonusTile>,
+    supply: BuildingSupply,
+    setup_spades: u32, // Left to use when placing the initial workshops
 }
 
 impl Faction {
@@ -33,6 +45,7 @@ impl Faction {
         let mut faction = Faction {
             race: *race,
             color: *color,
+            vp: VP(20),
             digging_cost: Tools(3),
             sailing_level: 0,
             tools: Tools(3),
@@ -46,6 +59,9 @@ impl Faction {
                 + &Resources::from(Coins(5))
                 + &Resources::from(Scholars(1)),
             sailing_upg_cost: Resources::from(Coins(4)) + &Resources::from(Scholars(1)),
+            bonus_tile: None,
+            supply: BuildingSupply::new(color),
+            setup_spades: 0,
         };
 
         faction.apply_race_bonus();
@@ -54,16 +70,271 @@ impl Faction {
         faction
     }
 
+    pub fn race(&self) -> Race {
+        self.race
+    }
+
     pub fn get_color(&self) -> Color {
         self.color
     }
 
+    pub fn vp(&self) -> VP {
+        self.vp
+    }
+
+    pub fn gain_vp(&mut self, amount: VP) {
+        self.vp = VP(self.vp.0 + amount.0);
+    }
+
+    /// Loses VP, although never below zero.
+    pub fn lose_vp(&mut self, amount: VP) {
+        self.vp = VP(self.vp.0.saturating_sub(amount.0));
+    }
+
+    pub fn power(&self) -> &PowerBowls {
+        &self.power
+    }
+
+    /// Gains power through the bowls and returns the amount actually gained.
+    pub fn gain_power(&mut self, amount: u32) -> u32 {
+        self.power.gain(amount)
+    }
+
+    pub fn digging_cost(&self) -> Tools {
+        self.digging_cost
+    }
+
+    pub fn tools(&self) -> Tools {
+        self.tools
+    }
+
+    pub fn sailing_level(&self) -> u32 {
+        self.sailing_level
+    }
+
+    pub fn setup_spades(&self) -> u32 {
+        self.setup_spades
+    }
+
+    pub(crate) fn use_setup_spades(&mut self, spades: u32) {
+        self.setup_spades -= spades;
+    }
+
+    pub fn supply(&self) -> &BuildingSupply {
+        &self.supply
+    }
+
+    /// Tools, coins, scholars, books (of each discipline) and spendable power held.
+    pub fn resources(&self) -> Resources {
+        Resources::from(self.tools)
+            + &Resources::from(self.coins)
+            + &Resources::from(self.scholars)
+            + &Resources::from(self.books)
+            + &Resources::from(Power(self.power.amount(3)))
+    }
+
+    /// Whether the faction holds enough tools, coins, scholars, books of each color and
+    /// power (in bowl 3).
+    pub fn can_afford(&self, cost: &Resources) -> bool {
+        let power: Power = cost.get();
+
+        self.tools >= cost.get()
+            && self.coins >= cost.get()
+            && self.scholars >= cost.get()
+            && all::<Discipline>().all(|disc| self.books(disc) >= cost.books(disc))
+            && self.power.amount(3) >= power.get_val()
+    }
+
+    /// Pays tools, coins, scholars, books and power (from bowl 3). Nothing is paid unless
+    /// the whole cost can be afforded.
+    pub fn pay(&mut self, cost: &Resources) -> Result<()> {
+        let tools: Tools = cost.get();
+        let coins: Coins = cost.get();
+        let scholars: Scholars = cost.get();
+        let power: Power = cost.get();
+
+        if !self.can_afford(cost) {
+            return Err(AoiError::NotEnoughResources {
+                required: *cost,
+                available: self.resources(),
+            });
+        }
+
+        self.tools -= tools;
+        self.coins -= coins;
+        self.scholars -= scholars;
+        for disc in all::<Discipline>() {
+            self.books[disc as usize] -= cost.books(disc);
+        }
+        self.power.spend(power.get_val())?;
+
+        Ok(())
+    }
+
+    /// Gains tools, coins, scholars (up to the scholar cap), books and power.
+    pub fn gain(&mut self, resources: &Resources) {
+        let tools: Tools = resources.get();
+        let coins: Coins = resources.get();
+        let scholars: Scholars = resources.get();
+        let power: Power = resources.get();
+
+        self.tools += tools;
+        self.coins += coins;
+        self.scholars = min(self.scholars + scholars, self.scholars_cap);
+        for disc in all::<Discipline>() {
+            self.books[disc as usize] += resources.books(disc);
+        }
+        self.power.gain(power.get_val());
+    }
+
+    /// Burns and spends power according to the conversion. Nothing changes unless all of
+    /// it can be made.
+    pub fn convert_power(&mut self, conversion: &Conversion) -> Result<()> {
+        let mut conv = power::start_conversion(self.power);
+        conv.burn_power(conversion.burn)?;
+        conv.convert_to_coins(conversion.coins)?;
+        conv.convert_to_tools(conversion.tools)?;
+        conv.convert_to_scholars(conversion.scholars)?;
+        if let Some((disc, amount)) = conversion.books {
+            conv.convert_to_books(disc, amount)?;
+        }
+
+        let (bowls, books, scholars, tools, coins) = power::finish_conversion(conv);
+        self.power = bowls;
+        self.gain(
+            &(Resources::from(scholars)
+                + &Resources::from(tools)
+                + &Resources::from(coins)
+                + &Resources::from(books)),
+        );
+
+        Ok(())
+    }
+
+    /// Income from all income tracks, the held bonus tile and the race.
+    pub fn total_income(&self) -> Resources {
+        let bonus_income = self
+            .bonus_tile
+            .map(|tile| tile.income())
+            .unwrap_or_else(Resources::none);
+
+        self.supply.income() + &bonus_income + &self.race.income_bonus()
+    }
+
+    pub fn collect_income(&mut self) {
+        let income = self.total_income();
+        self.gain(&income);
+    }
+
+    /// Takes a building from the supply without paying for it, e.g. during the initial
+    /// placement.
+    pub(crate) fn take_from_supply(&mut self, building: Building) -> Result<()> {
+        self.supply.take(building)
+    }
+
+    pub fn build_workshop(&mut self) -> Result<()> {
+        if self.supply.remaining(Building::Workshop) == 0 {
+            return Err(AoiError::NoneLeftInSupply {
+                building: Building::Workshop,
+            });
+        }
+
+        self.pay(&Building::Workshop.cost().unwrap())?;
+        self.supply.take(Building::Workshop)
+    }
+
+    /// Cost of upgrading into `to`. Guilds are cheaper to build next to other players.
+    pub fn upgrade_cost(&self, to: Building, has_neighbor: bool) -> Result<Resources> {
+        let cost = to.cost().ok_or(AoiError::NotPurchasable { building: to })?
+            - &self.race.upgrade_discount(to);
+
+        if to == Building::Guild && (has_neighbor || self.race.always_has_neighbor()) {
+            Ok(cost - &Resources::from(GUILD_NEIGHBOR_DISCOUNT))
+        } else {
+            Ok(cost)
+        }
+    }
+
+    /// Upgrades a building of kind `from` into `to`. The new building is taken from the
+    /// supply and the old one is put back on its income track.
+    pub fn upgrade(&mut self, from: Building, to: Building, has_neighbor: bool) -> Result<()> {
+        self.check_upgrade(from, to)?;
+
+        let cost = self.upgrade_cost(to, has_neighbor)?;
+        self.pay(&cost)?;
+        self.supply.take(to)?;
+        self.supply.put_back(from)
+    }
+
+    /// Upgrades like `upgrade`, but without paying for it.
+    pub(crate) fn upgrade_for_free(&mut self, from: Building, to: Building) -> Result<()> {
+        self.check_upgrade(from, to)?;
+
+        self.supply.take(to)?;
+        self.supply.put_back(from)
+    }
+
+    fn check_upgrade(&self, from: Building, to: Building) -> Result<()> {
+        if !from.can_upgrade_to(to) {
+            return Err(AoiError::InvalidUpgrade { from, to });
+        }
+        if self.supply.remaining(to) == 0 {
+            return Err(AoiError::NoneLeftInSupply { building: to });
+        }
+
+        Ok(())
+    }
+
+    /// Pays for the next level of sailing, reaching one hex further across water.
+    pub fn advance_sailing(&mut self) -> Result<()> {
+        if self.sailing_level >= MAX_SAILING_LEVEL {
+            return Err(AoiError::MaxSailingLevel);
+        }
+
+        let cost = self.sailing_upg_cost;
+        self.pay(&cost)?;
+        self.sailing_level += 1;
+
+        Ok(())
+    }
+
+    /// Pays for making every spade one tool cheaper.
+    pub fn advance_digging(&mut self) -> Result<()> {
+        if self.digging_cost <= MIN_DIGGING_COST {
+            return Err(AoiError::MinDiggingCost);
+        }
+
+        let cost = self.dig_upg_cost;
+        self.pay(&cost)?;
+        self.digging_cost -= Tools(1);
+
+        Ok(())
+    }
+
+    pub fn bonus_tile(&self) -> Option<BonusTile> {
+        self.bonus_tile
+    }
+
+    /// Replaces the held bonus tile and returns the previous one.
+    pub fn set_bonus_tile(&mut self, tile: Option<BonusTile>) -> Option<BonusTile> {
+        std::mem::replace(&mut self.bonus_tile, tile)
+    }
+
+    pub fn books(&self, disc: Discipline) -> Books {
+        self.books[disc as usize]
+    }
+
+    pub fn disc_level(&self, disc: Discipline) -> u32 {
+        self.disc_track[disc as usize]
+    }
+
     pub fn incr_disc(&mut self, disc: Discipline, amount: u32) -> u32 {
         let track: &mut u32 = &mut self.disc_track[disc as usize];
         *track = min(*track + amount, DISCIPLINE_MAX);
         *track
     }
 
+    // Starting steps and tools. The race abilities apply where they are used, see Race.
     fn apply_race_bonus(&mut self) {
         match self.race {
             Race::Blessed => {
@@ -71,60 +342,48 @@ impl Faction {
                 self.incr_disc(Discipline::Law, 1);
                 self.incr_disc(Discipline::Engineering, 1);
                 self.incr_disc(Discipline::Medicine, 1);
-                // TODO: Ability
             }
             Race::Monks => {
                 self.incr_disc(Discipline::Law, 1);
                 self.incr_disc(Discipline::Medicine, 1);
 
                 self.tools += Tools(1);
-                // TODO: Ability
             }
             Race::Felines => {
                 self.incr_disc(Discipline::Banking, 1);
                 self.incr_disc(Discipline::Medicine, 1);
-                // TODO: Ability
             }
             Race::Navigators => {
                 self.incr_disc(Discipline::Law, 3);
-                // TODO: Ability
             }
             Race::Goblins => {
                 self.incr_disc(Discipline::Banking, 1);
                 self.incr_disc(Discipline::Engineering, 1);
 
                 self.tools += Tools(1);
-                // TODO: Ability
             }
             Race::Omar => {
                 self.incr_disc(Discipline::Banking, 1);
                 self.incr_disc(Discipline::Engineering, 1);
-                // TODO: Ability
             }
             Race::Illusionists => {
                 self.incr_disc(Discipline::Medicine, 2);
-                // TODO: Ability
-            }
-            Race::Inventors => {
-                // TODO: Ability
             }
+            Race::Inventors => {}
             Race::Philosophers => {
                 self.incr_disc(Discipline::Banking, 2);
-                // TODO: Ability
             }
             Race::Lizards => {
-                // TODO: Ability
+                self.digging_cost -= Tools(1);
             }
             Race::Psychics => {
                 self.incr_disc(Discipline::Banking, 1);
                 self.incr_disc(Discipline::Medicine, 1);
 
                 self.tools += Tools(1);
-                // TODO: Ability
             }
             Race::Moles => {
                 self.incr_disc(Discipline::Engineering, 2);
-                // TODO: Ability
             }
             Race::Raceless => {
                 // For testing, no bonus
@@ -133,112 +392,60 @@ impl Faction {
     }
 
     fn apply_color_bonus(&mut self) {
-        match self.color {
-            Color::Yellow => {
-                // TODO: Gain spade
-            }
-            Color::Brown => {
-                self.dig_upg_cost = Resources::from(Tools(1))
-                    + &Resources::from(Coins(1))
-                    + &Resources::from(Scholars(1));
-            }
-            Color::Black => {
-                self.scholars += Scholars(1);
-                self.power = PowerBowls::new(3, 9, 0);
-            }
-            Color::Blue => {
-                self.sailing_level = 1;
-            }
-            Color::Green => {
-                self.incr_disc(Discipline::Banking, 1);
-                self.incr_disc(Discipline::Law, 1);
-                self.incr_disc(Discipline::Engineering, 1);
-                self.incr_disc(Discipline::Medicine, 1);
+        let bonus = colorbonus::color_bonus(self.color);
 
-                self.power = PowerBowls::new(4, 8, 0);
-            }
-            Color::Gray => {
-                // TODO...
-            }
-            Color::Red => {
-                // TODO...
-            }
-            Color::Colorless => {
-                // For testing, no bonus
-            }
+        for disc in all::<Discipline>() {
+            self.incr_disc(disc, bonus.disc_steps[disc as usize]);
         }
+        if let Some([bowl1, bowl2, bowl3]) = bonus.power {
+            self.power = PowerBowls::new(bowl1, bowl2, bowl3);
+        }
+        self.gain(&bonus.resources);
+        self.sailing_level = bonus.sailing_level;
+        if let Some(cost) = bonus.dig_upg_cost {
+            self.dig_upg_cost = cost;
+        }
+        self.setup_spades = bonus.setup_spades;
     }
 }
 
+#[derive(Clone)]
 pub struct IncomeTrack {
-    income_gain: Vec<Resources>, // zeroth index is base income (not any buildings placed)
-    num_occupied: usize,         // Number of occupied building slots on the income track
+    income_gain: Arc<[Resources]>, // zeroth index is base income (not any buildings placed)
+    num_occupied: usize,           // Number of occupied building slots on the income track
 }
 
 impl IncomeTrack {
-    // TODO: Fill in the rest...
-    pub fn new(color: &Color, building: &Building) -> Result<Self> {
-        match (color, building) {
-            (Color::Gray, Building::Workshop) => Ok(IncomeTrack {
-                income_gain: vec![
-                    Resources::from(Tools(1)) + &Resources::from(Coins(2)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(0)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                ],
-                num_occupied: 9,
-            }),
-            (_, Building::Workshop) => Ok(IncomeTrack {
-                income_gain: vec![
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(0)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                    Resources::from(Tools(1)),
-                ],
-                num_occupied: 9,
-            }),
-            (_, Building::Guild) => Ok(IncomeTrack {
-                income_gain: vec![
-                    Resources::none(),
-                    Resources::from(Coins(2)) + &Resources::from(Power(1)),
-                    Resources::from(Coins(2)) + &Resources::from(Power(1)),
-                    Resources::from(Coins(2)) + &Resources::from(Power(2)),
-                    Resources::from(Coins(2)) + &Resources::from(Power(2)),
-                ],
-                num_occupied: 4,
-            }),
-            (_, Building::School) => Ok(IncomeTrack {
-                income_gain: vec![
-                    Resources::none(),
-                    Resources::from(Scholars(1)),
-                    Resources::from(Scholars(1)),
-                    Resources::from(Scholars(1)),
-                ],
-                num_occupied: 3,
-            }),
-            (_, Building::University) => Ok(IncomeTrack {
-                income_gain: vec![Resources::none(), Resources::from(Scholars(1))],
-                num_occupied: 1,
-            }),
-            _ => Err(create_error("No income track for specified building")),
+    pub fn new(color: &Color, building: &Building) -> Self {
+        let slots: Vec<Resources> = match building {
+            Building::Workshop => [1, 1, 1, 1, 0, 1, 1, 1, 1]
+                .into_iter()
+                .map(|tools| Resources::from(Tools(tools)))
+                .collect(),
+            Building::Guild => [(2, 1), (2, 1), (2, 2), (2, 2)]
+                .into_iter()
+                .map(|(coins, power)| {
+                    Resources::from(Coins(coins)) + &Resources::from(Power(power))
+                })
+                .collect(),
+            Building::School => vec![Resources::from(Scholars(1)); 3],
+            Building::University => vec![Resources::from(Scholars(1))],
+            Building::Palace => vec![palace_income(color)],
+            Building::Tower | Building::Monument => vec![Resources::none()],
+        };
+
+        let mut income_gain = vec![base_income(color, building)];
+        income_gain.extend(slots);
+
+        IncomeTrack {
+            num_occupied: income_gain.len() - 1,
+            income_gain: income_gain.into(),
         }
     }
 
     pub fn remove_building(&mut self) -> Result<()> {
         if self.num_occupied == 0 {
-            Err(create_error("No workshops left on board"))
+            Err(AoiError::IncomeTrackEmpty)
         } else {
             self.num_occupied -= 1;
 
@@ -247,8 +454,8 @@ impl IncomeTrack {
     }
 
     pub fn put_building(&mut self) -> Result<()> {
-        if self.num_occupied >= 9 {
-            Err(create_error("Board is already full of workshops"))
+        if self.num_occupied >= self.income_gain.len() - 1 {
+            Err(AoiError::IncomeTrackFull)
         } else {
             self.num_occupied += 1;
 
@@ -256,6 +463,11 @@ impl IncomeTrack {
         }
     }
 
+    /// Number of buildings still on the track, i.e. not yet built.
+    pub fn remaining(&self) -> usize {
+        self.num_occupied
+    }
+
     pub fn income(&self) -> Resources {
         let num_income_slots = self.income_gain.len() - self.num_occupied;
 
@@ -266,23 +478,38 @@ impl IncomeTrack {
     }
 }
 
+// Income gained regardless of how many buildings have been built.
+fn base_income(color: &Color, building: &Building) -> Resources {
+    match building {
+        Building::Workshop => {
+            Resources::from(Tools(1)) + &colorbonus::color_bonus(*color).workshop_income
+        }
+        _ => Resources::none(),
+    }
+}
+
+fn palace_income(color: &Color) -> Resources {
+    colorbonus::color_bonus(*color).palace_income
+}
+
 #[cfg(test)]
 mod tests {
     use assert_json_diff::assert_json_include;
+    use parameterized::parameterized;
     use serde_json::json;
 
     use super::*;
 
     #[test]
     fn income_for_zero_workshops() {
-        let track = IncomeTrack::new(&Color::Yellow, &Building::Workshop).unwrap(); // Arbitrary color
+        let track = IncomeTrack::new(&Color::Yellow, &Building::Workshop); // Arbitrary color
 
         assert_eq!(track.income(), Resources::from(Tools(1)));
     }
 
     #[test]
     fn income_for_two_workshops() {
-        let mut track = IncomeTrack::new(&Color::Yellow, &Building::Workshop).unwrap(); // Arbitrary color
+        let mut track = IncomeTrack::new(&Color::Yellow, &Building::Workshop); // Arbitrary color
 
         track.remove_building().unwrap();
         track.remove_building().unwrap();
@@ -292,7 +519,7 @@ mod tests {
 
     #[test]
     fn income_for_nine_workshops() {
-        let mut track = IncomeTrack::new(&Color::Black, &Building::Workshop).unwrap();
+        let mut track = IncomeTrack::new(&Color::Black, &Building::Workshop);
 
         for _ in 0..9 {
             track.remove_building().unwrap();
@@ -303,7 +530,7 @@ mod tests {
 
     #[test]
     fn gray_has_extra_gold_income() {
-        let mut track = IncomeTrack::new(&Color::Gray, &Building::Workshop).unwrap();
+        let mut track = IncomeTrack::new(&Color::Gray, &Building::Workshop);
 
         track.remove_building().unwrap();
 
@@ -313,9 +540,96 @@ mod tests {
         );
     }
 
+    #[parameterized(color = { Color::Yellow, Color::Brown, Color::Black, Color::Blue, Color::Green, Color::Gray, Color::Red })]
+    fn every_building_has_an_income_track(color: Color) {
+        for building in enum_iterator::all::<Building>() {
+            let mut track = IncomeTrack::new(&color, &building);
+
+            // Nothing built yet, so only the base income is gained
+            assert_eq!(track.income(), base_income(&color, &building));
+
+            while track.remaining() > 0 {
+                track.remove_building().unwrap();
+            }
+            assert!(track.remove_building().is_err());
+        }
+    }
+
+    #[test]
+    fn income_for_all_guilds() {
+        let mut track = IncomeTrack::new(&Color::Blue, &Building::Guild);
+
+        for _ in 0..4 {
+            track.remove_building().unwrap();
+        }
+
+        assert_eq!(
+            track.income(),
+            Resources::from(Coins(8)) + &Resources::from(Power(6))
+        );
+    }
+
+    #[test]
+    fn palace_income_depends_on_color() {
+        let mut red = IncomeTrack::new(&Color::Red, &Building::Palace);
+        let mut black = IncomeTrack::new(&Color::Black, &Building::Palace);
+        assert_eq!(red.income(), Resources::none());
+
+        red.remove_building().unwrap();
+        black.remove_building().unwrap();
+
+        assert_eq!(red.income(), Resources::from(Power(6)));
+        assert_eq!(
+            black.income(),
+            Resources::from(Power(2)) + &Resources::from(Scholars(1))
+        );
+    }
+
+    #[test]
+    fn sailing_stops_at_max_level() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.gain(&(Resources::from(Coins(5)) + &Resources::from(Scholars(3))));
+
+        for _ in 0..MAX_SAILING_LEVEL {
+            faction.advance_sailing().unwrap();
+        }
+
+        assert_eq!(faction.sailing_level(), MAX_SAILING_LEVEL);
+        assert_eq!(faction.advance_sailing(), Err(AoiError::MaxSailingLevel));
+    }
+
+    #[test]
+    fn digging_gets_cheaper_down_to_min_cost() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.gain(&Resources::from(Scholars(2)));
+
+        faction.advance_digging().unwrap();
+        faction.advance_digging().unwrap();
+
+        assert_eq!(faction.digging_cost(), MIN_DIGGING_COST);
+        assert_eq!(faction.advance_digging(), Err(AoiError::MinDiggingCost));
+    }
+
+    #[test]
+    fn total_income_sums_all_tracks_and_bonus_tile() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Gray);
+        faction.take_from_supply(Building::Workshop).unwrap();
+        faction.take_from_supply(Building::School).unwrap();
+        faction.take_from_supply(Building::Palace).unwrap();
+        faction.set_bonus_tile(Some(BonusTile::BonCoins));
+
+        assert_eq!(
+            faction.total_income(),
+            Resources::from(Tools(3))
+                + &Resources::from(Coins(8))
+                + &Resources::from(Scholars(1))
+                + &Resources::from(Power(3))
+        );
+    }
+
     #[test]
     fn remove_to_many_from_income_track() {
-        let mut track = IncomeTrack::new(&Color::Yellow, &Building::Workshop).unwrap();
+        let mut track = IncomeTrack::new(&Color::Yellow, &Building::Workshop);
 
         for _ in 0..9 {
             track.remove_building().unwrap();
@@ -326,7 +640,7 @@ mod tests {
 
     #[test]
     fn put_building_on_track_when_full() {
-        let mut track = IncomeTrack::new(&Color::Yellow, &Building::Workshop).unwrap();
+        let mut track = IncomeTrack::new(&Color::Yellow, &Building::Workshop);
 
         assert!(track.put_building().is_err());
     }
@@ -356,6 +670,118 @@ mod tests {
         assert_eq!(disc, 12);
     }
 
+    #[test]
+    fn pay_is_all_or_nothing() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        let too_expensive = Resources::from(Tools(1)) + &Resources::from(Coins(100));
+
+        assert!(faction.pay(&too_expensive).is_err());
+        assert_eq!(faction.tools(), Tools(3));
+
+        faction
+            .pay(&(Resources::from(Tools(2)) + &Resources::from(Coins(5))))
+            .unwrap();
+        assert_eq!(faction.tools(), Tools(1));
+        assert_eq!(faction.coins, Coins(10));
+    }
+
+    #[test]
+    fn income_comes_from_bonus_tile() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        assert_eq!(faction.total_income(), Resources::from(Tools(1))); // Base income
+
+        faction.set_bonus_tile(Some(BonusTile::BonCoins));
+        faction.collect_income();
+
+        assert_eq!(faction.coins, Coins(21));
+    }
+
+    #[test]
+    fn scholars_gained_are_capped() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        faction.gain(&Resources::from(Scholars(10)));
+
+        assert_eq!(faction.scholars, faction.scholars_cap);
+    }
+
+    #[test]
+    fn build_workshop_charges_cost_and_takes_from_supply() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        faction.build_workshop().unwrap();
+
+        assert_eq!(faction.tools, Tools(2));
+        assert_eq!(faction.coins, Coins(13));
+        assert_eq!(faction.supply().remaining(Building::Workshop), 8);
+        assert_eq!(faction.total_income(), Resources::from(Tools(2)));
+    }
+
+    #[test]
+    fn upgrade_workshop_to_guild() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.take_from_supply(Building::Workshop).unwrap();
+
+        faction
+            .upgrade(Building::Workshop, Building::Guild, false)
+            .unwrap();
+
+        assert_eq!(faction.tools, Tools(1));
+        assert_eq!(faction.coins, Coins(9));
+        assert_eq!(faction.supply().remaining(Building::Workshop), 9);
+        assert_eq!(faction.supply().remaining(Building::Guild), 3);
+        assert_eq!(
+            faction.total_income(),
+            Resources::from(Tools(1)) + &Resources::from(Coins(2)) + &Resources::from(Power(1))
+        );
+    }
+
+    #[test]
+    fn guild_next_to_neighbor_is_cheaper() {
+        let faction = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        let alone: Coins = faction.upgrade_cost(Building::Guild, false).unwrap().get();
+        let neighbor: Coins = faction.upgrade_cost(Building::Guild, true).unwrap().get();
+
+        assert_eq!(alone, Coins(6));
+        assert_eq!(neighbor, Coins(3));
+    }
+
+    #[test]
+    fn upgrade_must_follow_upgrade_graph() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.tools = Tools(20);
+        faction.coins = Coins(20);
+
+        assert!(faction
+            .upgrade(Building::Workshop, Building::School, false)
+            .is_err());
+        assert!(faction
+            .upgrade(Building::Guild, Building::University, false)
+            .is_err());
+        assert_eq!(faction.tools, Tools(20));
+        assert_eq!(faction.coins, Coins(20));
+    }
+
+    #[test]
+    fn upgrade_without_enough_resources_changes_nothing() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.take_from_supply(Building::Workshop).unwrap();
+        faction.take_from_supply(Building::Guild).unwrap();
+
+        faction
+            .upgrade(Building::Guild, Building::School, false)
+            .unwrap();
+
+        // A university costs more tools than are left
+        assert!(faction
+            .upgrade(Building::School, Building::University, false)
+            .is_err());
+        assert_eq!(faction.tools, Tools(0));
+        assert_eq!(faction.supply().remaining(Building::School), 2);
+        assert_eq!(faction.supply().remaining(Building::University), 1);
+    }
+
     #[test]
     fn faction_blessed_has_correct_starting_state() {
         let blessed = Faction::new(&Race::Blessed, &Color::Colorless);
@@ -368,7 +794,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [1, 1, 1, 1],
@@ -398,7 +824,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 4,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 1, 0, 1],
@@ -428,7 +854,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [1, 0, 0, 1],
@@ -458,7 +884,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 3, 0, 0],
@@ -488,7 +914,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 4,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [1, 0, 1, 0],
@@ -518,7 +944,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [1, 0, 1, 0],
@@ -548,7 +974,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 0, 0, 2],
@@ -578,7 +1004,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 0, 0, 0],
@@ -608,7 +1034,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [2, 0, 0, 0],
@@ -634,11 +1060,11 @@ mod tests {
         let expected = json!({
             "race": "Lizards",
             "color": "Colorless",
-            "digging_cost": 3,
+            "digging_cost": 2,
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 0, 0, 0],
@@ -668,7 +1094,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 4,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [1, 0, 0, 1],
@@ -698,7 +1124,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 0, 2, 0],
@@ -724,11 +1150,12 @@ mod tests {
         let expected = json!({
             "race": "Raceless",
             "color": "Yellow",
+            "setup_spades": 1,
             "digging_cost": 3,
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 0, 0, 0],
@@ -746,6 +1173,35 @@ mod tests {
         assert_json_include!(actual: json, expected: expected);
     }
 
+    #[test]
+    fn gray_has_correct_starting_state() {
+        let faction = Faction::new(&Race::Raceless, &Color::Gray);
+        let json = serde_json::to_value(&faction).unwrap();
+
+        let expected = json!({
+            "color": "Gray",
+            "tools": 4,
+            "coins": 15,
+            "power": [5, 7, 0],
+            "setup_spades": 0,
+        });
+        assert_json_include!(actual: json, expected: expected);
+    }
+
+    #[test]
+    fn red_has_correct_starting_state() {
+        let faction = Faction::new(&Race::Raceless, &Color::Red);
+        let json = serde_json::to_value(&faction).unwrap();
+
+        let expected = json!({
+            "color": "Red",
+            "tools": 3,
+            "power": [5, 5, 2],
+            "setup_spades": 0,
+        });
+        assert_json_include!(actual: json, expected: expected);
+    }
+
     #[test]
     fn brown_has_correct_starting_state() {
         let faction = Faction::new(&Race::Raceless, &Color::Brown);
@@ -758,7 +1214,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 0,
             "scholars_cap": 7,
             "disc_track": [0, 0, 0, 0],
@@ -788,7 +1244,7 @@ mod tests {
             "sailing_level": 0,
             "tools": 3,
             "coins": 15,
-            "books": [0, 0, 0, 0],
+            "books": { "Banking": 0, "Law": 0, "Engineering": 0, "Medicine": 0 },
             "scholars": 1,
             "scholars_cap": 7,
             "disc_track": [0, 0, 0, 0],
@@ -805,4 +1261,65 @@ mod tests {
         });
         assert_json_include!(actual: json, expected: expected);
     }
+
+    #[test]
+    fn burn_and_convert_power() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless); // Bowls [5, 7, 0]
+        let conversion = Conversion {
+            burn: 3,
+            tools: Tools(1),
+            ..Default::default()
+        };
+
+        faction.convert_power(&conversion).unwrap();
+
+        let json = serde_json::to_value(&faction).unwrap();
+        assert_json_include!(
+            actual: json,
+            expected: json!({ "power": [8, 1, 0], "tools": 4, "coins": 15 })
+        );
+    }
+
+    #[test]
+    fn failed_conversion_changes_nothing() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless); // Bowls [5, 7, 0]
+        let conversion = Conversion {
+            burn: 3,
+            scholars: Scholars(1),
+            ..Default::default()
+        };
+
+        assert!(faction.convert_power(&conversion).is_err());
+
+        let json = serde_json::to_value(&faction).unwrap();
+        assert_json_include!(
+            actual: json,
+            expected: json!({ "power": [5, 7, 0], "scholars": 0 })
+        );
+    }
+
+    #[test]
+    fn power_is_converted_to_books_of_picked_color() {
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        faction.gain_power(12); // Bowls [0, 5, 7]
+        let conversion = Conversion {
+            books: Some((Discipline::Engineering, Books(1))),
+            ..Default::default()
+        };
+
+        faction.convert_power(&conversion).unwrap();
+
+        assert_eq!(faction.books(Discipline::Engineering), Books(1));
+        assert_eq!(faction.resources().total_books(), Books(1));
+        let json = serde_json::to_value(&faction).unwrap();
+        assert_json_include!(
+            actual: json,
+            expected: json!({
+                "power": [5, 5, 2],
+                "books": { "Banking": 0, "Law": 0, "Engineering": 1, "Medicine": 0 }
+            })
+        );
+        let resources = serde_json::to_value(faction.resources()).unwrap();
+        assert_eq!(resources["books"], json!({ "Engineering": 1 }));
+    }
 }
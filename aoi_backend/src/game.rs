@@ -0,0 +1,414 @@
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::bonustile::BonusTile;
+use crate::building::Building;
+use crate::common::{Color, Discipline};
+use crate::gamephase::actionphase::ActionPhase;
+use crate::gamephase::buildingplacement::BuildingPlacer;
+use crate::gamephase::cleanupphase::{CleanupPhase, NextPhase};
+use crate::gamephase::finalscoring::{FinalScore, FinalScoring};
+use crate::gamephase::{GameState, PlayerId};
+use crate::leech::LeechOffer;
+use crate::map::Pos;
+use crate::power::Conversion;
+use crate::resources::Books;
+use crate::{error::AoiError, Result};
+
+/// Everything a player can do in the game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Action {
+    /// Places an initial workshop before the first round.
+    Place {
+        pos: Pos,
+    },
+    Build {
+        pos: Pos,
+    },
+    Upgrade {
+        pos: Pos,
+        to: Building,
+    },
+    /// Terraforms a hex without building on it.
+    Terraform {
+        pos: Pos,
+        to: Color,
+    },
+    BuildBridge {
+        from: Pos,
+        to: Pos,
+    },
+    AdvanceSailing,
+    AdvanceDigging,
+    ConvertPower(Conversion),
+    /// Takes back the last action of the turn.
+    Undo,
+    Redo,
+    /// Takes back every action of the turn.
+    AbortTurn,
+    EndTurn,
+    Leech {
+        accept: bool,
+    },
+    Pass {
+        bonus_tile: Option<BonusTile>,
+    },
+    /// Digs a hex with spades rewarded at the end of a round.
+    Dig {
+        pos: Pos,
+    },
+    DeclineSpades,
+    /// Takes the book action at `idx`, paying with books of the picked colors.
+    BookAction {
+        idx: usize,
+        payment: Vec<(Discipline, Books)>,
+        #[serde(default)]
+        disc: Option<Discipline>, // The discipline to advance
+        #[serde(default)]
+        pos: Option<Pos>, // The workshop to upgrade or the hex to dig
+    },
+}
+
+/// A game from the placement of the initial buildings until final scoring. Phases without
+/// any decisions for the players are passed through as soon as they are reached.
+#[derive(Clone)]
+pub enum Game {
+    BuildingPlacement(BuildingPlacer),
+    Action(ActionPhase),
+    Cleanup(CleanupPhase),
+    FinalScoring(FinalScoring),
+}
+
+impl Game {
+    pub fn new(placer: BuildingPlacer) -> Self {
+        Game::BuildingPlacement(placer)
+    }
+
+    pub fn state(&self) -> &GameState {
+        match self {
+            Game::BuildingPlacement(phase) => phase.state(),
+            Game::Action(phase) => phase.state(),
+            Game::Cleanup(phase) => phase.state(),
+            Game::FinalScoring(phase) => phase.state(),
+        }
+    }
+
+    /// The player expected to act next. Players with pending power offers may act out of
+    /// turn by accepting or declining.
+    pub fn player_to_move(&self) -> Option<PlayerId> {
+        match self {
+            Game::BuildingPlacement(phase) => phase.player_to_move().ok(),
+            Game::Action(phase) => phase.player_to_move().ok(),
+            Game::Cleanup(phase) => phase.player_to_move().ok(),
+            Game::FinalScoring(_) => None,
+        }
+    }
+
+    pub fn pending_leech(&self) -> &[LeechOffer] {
+        match self {
+            Game::Action(phase) => phase.pending_leech(),
+            _ => &[],
+        }
+    }
+
+    /// Spades the player to move has left to dig at the end of a round.
+    pub fn spades_left(&self) -> u32 {
+        match self {
+            Game::Cleanup(phase) => phase.spades_left(),
+            _ => 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Game::FinalScoring(_))
+    }
+
+    /// The breakdown of the final scoring, once the game is over.
+    pub fn final_scores(&self) -> Option<&Vec<FinalScore>> {
+        match self {
+            Game::FinalScoring(phase) => Some(phase.scores()),
+            _ => None,
+        }
+    }
+
+    /// Applies the action of a player, moving on to the next phase once the current one
+    /// is over. A rejected action leaves the game unchanged.
+    pub fn apply(&mut self, player_id: PlayerId, action: &Action) -> Result<()> {
+        match (&mut *self, action) {
+            (Game::BuildingPlacement(phase), Action::Place { pos }) => {
+                phase.place(player_id, *pos)?
+            }
+            (Game::Action(phase), Action::Build { pos }) => phase.build(player_id, *pos)?,
+            (Game::Action(phase), Action::Upgrade { pos, to }) => {
+                phase.upgrade(player_id, *pos, *to)?
+            }
+            (Game::Action(phase), Action::Terraform { pos, to }) => {
+                phase.terraform(player_id, *pos, *to)?
+            }
+            (Game::Action(phase), Action::BuildBridge { from, to }) => {
+                phase.build_bridge(player_id, *from, *to)?
+            }
+            (Game::Action(phase), Action::AdvanceSailing) => phase.advance_sailing(player_id)?,
+            (Game::Action(phase), Action::AdvanceDigging) => phase.advance_digging(player_id)?,
+            (Game::Action(phase), Action::ConvertPower(conversion)) => {
+                phase.convert_power(player_id, conversion)?
+            }
+            (
+                Game::Action(phase),
+                Action::BookAction {
+                    idx,
+                    payment,
+                    disc,
+                    pos,
+                },
+            ) => phase.book_action(player_id, *idx, payment, *disc, *pos)?,
+            (Game::Action(phase), Action::Undo) => phase.undo(player_id)?,
+            (Game::Action(phase), Action::Redo) => phase.redo(player_id)?,
+            (Game::Action(phase), Action::AbortTurn) => phase.abort_turn(player_id)?,
+            (Game::Action(phase), Action::EndTurn) => phase.end_turn(player_id)?,
+            (Game::Action(phase), Action::Leech { accept }) => phase.leech(player_id, *accept)?,
+            (Game::Action(phase), Action::Pass { bonus_tile }) => {
+                phase.pass(player_id, *bonus_tile)?
+            }
+            (Game::Cleanup(phase), Action::Dig { pos }) => phase.dig(player_id, *pos)?,
+            (Game::Cleanup(phase), Action::DeclineSpades) => phase.decline_spades(player_id)?,
+            _ => {
+                return Err(AoiError::ActionNotAllowed {
+                    action: action.clone(),
+                })
+            }
+        }
+
+        if self.phase_is_over() {
+            // The phases hand their state over by value. Nothing may panic in between,
+            // since there is no game to leave behind
+            replace_with::replace_with_or_abort(self, Game::next_phase);
+        }
+
+        Ok(())
+    }
+
+    /// A copy for trying out actions, leaving out the history kept for undo and redo.
+    pub(crate) fn lookahead(&self) -> Game {
+        match self {
+            Game::Action(phase) => Game::Action(phase.without_history()),
+            _ => self.clone(),
+        }
+    }
+
+    fn phase_is_over(&self) -> bool {
+        match self {
+            Game::BuildingPlacement(phase) => phase.player_to_move().is_err(),
+            Game::Action(phase) => phase.player_to_move().is_err(),
+            Game::Cleanup(phase) => phase.player_to_move().is_err(),
+            Game::FinalScoring(_) => false,
+        }
+    }
+
+    // Must only be called once the phase is over
+    fn next_phase(self) -> Game {
+        match self {
+            Game::BuildingPlacement(phase) => {
+                let income = phase.finish().expect("All buildings are placed");
+                Game::Action(income.finish())
+            }
+            Game::Action(phase) => {
+                let cleanup = Game::Cleanup(phase.finish().expect("All players have passed"));
+                if cleanup.phase_is_over() {
+                    cleanup.next_phase()
+                } else {
+                    cleanup
+                }
+            }
+            Game::Cleanup(phase) => match phase.finish().expect("All spades are dug") {
+                NextPhase::Income(income) => Game::Action(income.finish()),
+                NextPhase::FinalScoring(scoring) => Game::FinalScoring(scoring),
+            },
+            Game::FinalScoring(_) => self,
+        }
+    }
+
+    fn phase_name(&self) -> &'static str {
+        match self {
+            Game::BuildingPlacement(_) => "BuildingPlacement",
+            Game::Action(_) => "Action",
+            Game::Cleanup(_) => "Cleanup",
+            Game::FinalScoring(_) => "FinalScoring",
+        }
+    }
+}
+
+impl Serialize for Game {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut game = serializer.serialize_struct("Game", 6)?;
+        game.serialize_field("phase", self.phase_name())?;
+        game.serialize_field("player_to_move", &self.player_to_move())?;
+        game.serialize_field("pending_leech", self.pending_leech())?;
+        game.serialize_field("spades_left", &self.spades_left())?;
+        game.serialize_field("state", self.state())?;
+        game.serialize_field("final_scores", &self.final_scores())?;
+        game.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bookaction::{BookAction, BookActionEffect};
+    use crate::faction::Faction;
+    use crate::map;
+    use crate::race::Race;
+    use crate::resources::Resources;
+
+    use parameterized::parameterized;
+    use serde_json::json;
+
+    fn create_game(num_players: usize) -> Game {
+        let factions = (0..num_players)
+            .map(|_| Faction::new(&Race::Raceless, &Color::Colorless))
+            .collect();
+
+        Game::new(BuildingPlacer::new(
+            factions,
+            map::open_map(map::MapId::Debug),
+        ))
+    }
+
+    fn place_all(game: &mut Game) {
+        let map = game.state().map();
+        let mut positions = map::all_positions()
+            .filter(|&(r, c)| map[r][c].terrain != map::Terrain::Water)
+            .collect::<Vec<_>>()
+            .into_iter();
+        while let Game::BuildingPlacement(placer) = game {
+            let player_id = placer.player_to_move().unwrap();
+            let pos = positions.next().unwrap();
+            game.apply(player_id, &Action::Place { pos }).unwrap();
+        }
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn game_proceeds_to_actions_after_placement(num_players: usize) {
+        let mut game = create_game(num_players);
+
+        place_all(&mut game);
+
+        assert!(matches!(game, Game::Action(_)));
+        assert_eq!(game.player_to_move(), Some(0));
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn game_is_finished_after_last_round(num_players: usize) {
+        let mut game = create_game(num_players);
+        place_all(&mut game);
+
+        while let Some(player_id) = game.player_to_move() {
+            let pass = Action::Pass { bonus_tile: None };
+            game.apply(player_id, &pass).unwrap();
+        }
+
+        assert!(game.is_finished());
+        assert_eq!(game.state().round(), 5);
+    }
+
+    #[test]
+    fn action_of_other_phase_is_rejected() {
+        let mut game = create_game(2);
+
+        let action = Action::Pass { bonus_tile: None };
+        assert_eq!(
+            game.apply(0, &action),
+            Err(AoiError::ActionNotAllowed { action })
+        );
+    }
+
+    #[test]
+    fn turn_is_undone_through_actions() {
+        let mut game = create_game(2);
+        place_all(&mut game);
+        let conversion = Action::ConvertPower(Conversion {
+            burn: 1,
+            coins: crate::resources::Coins(1),
+            ..Default::default()
+        });
+        let before = serde_json::to_value(&game).unwrap();
+
+        game.apply(0, &conversion).unwrap();
+        game.apply(0, &conversion).unwrap();
+        game.apply(0, &Action::Undo).unwrap();
+        game.apply(0, &Action::Redo).unwrap();
+        game.apply(0, &Action::AbortTurn).unwrap();
+
+        assert_eq!(serde_json::to_value(&game).unwrap(), before);
+    }
+
+    #[test]
+    fn actions_are_deserialized_from_json() {
+        let build: Action =
+            serde_json::from_value(json!({ "type": "Build", "pos": [1, 2] })).unwrap();
+        let convert: Action =
+            serde_json::from_value(json!({ "type": "ConvertPower", "burn": 2 })).unwrap();
+
+        assert_eq!(build, Action::Build { pos: (1, 2) });
+        assert_eq!(
+            convert,
+            Action::ConvertPower(Conversion {
+                burn: 2,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn game_serializes_phase_and_state() {
+        let game = create_game(2);
+
+        let json = serde_json::to_value(&game).unwrap();
+
+        assert_eq!(json["phase"], "BuildingPlacement");
+        assert_eq!(json["player_to_move"], 0);
+        assert_eq!(json["state"]["factions"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            json["state"]["map"].as_array().unwrap().len(),
+            map::MAP_HEIGHT
+        );
+    }
+
+    #[test]
+    fn book_action_is_taken_through_actions() {
+        let factions = (0..2)
+            .map(|_| {
+                let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+                faction.gain(&Resources::from((Discipline::Banking, Books(1))));
+                faction
+            })
+            .collect();
+        let mut state = GameState::new(factions, map::open_map(map::MapId::Debug));
+        state.book_actions = vec![BookAction::new(Books(1), BookActionEffect::GainPower)];
+        let mut game = Game::new(BuildingPlacer::from_state(state));
+        place_all(&mut game);
+        let action: Action = serde_json::from_value(json!({
+            "type": "BookAction",
+            "idx": 0,
+            "payment": [["Banking", 1]],
+        }))
+        .unwrap();
+        let gain_limit = game.state().factions()[0].power().gain_limit();
+
+        game.apply(0, &action).unwrap();
+        game.apply(0, &Action::EndTurn).unwrap();
+
+        let faction = &game.state().factions()[0];
+        assert_eq!(faction.books(Discipline::Banking), Books(0));
+        assert_eq!(faction.power().gain_limit(), gain_limit - 5);
+        assert_eq!(
+            game.apply(1, &action),
+            Err(AoiError::BookActionTaken { idx: 0 })
+        );
+    }
+}
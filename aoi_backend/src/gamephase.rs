@@ -1,4 +1,131 @@
+pub mod actionphase;
 pub mod buildingplacement;
+pub mod cleanupphase;
+pub mod finalscoring;
 pub mod incomephase;
 
-pub type PlayerId = usize;
\ No newline at end of file
+use crate::bonustile::BonusTile;
+use crate::bookaction::BookAction;
+use crate::common::VP;
+use crate::faction::Faction;
+use crate::map;
+use crate::scoringtile::{ScoringEvent, ScoringTile};
+
+use serde::Serialize;
+
+pub type PlayerId = usize;
+
+pub const NUM_ROUNDS: usize = 6;
+
+/// Everything on the table once the factions have been selected. The state is handed
+/// over from one game phase to the next.
+#[derive(Clone, Serialize)]
+pub struct GameState {
+    pub(crate) factions: Vec<Faction>, // Indices map to player IDs
+    pub(crate) map: map::Map,
+    pub(crate) bridges: Vec<map::Bridge>,
+    pub(crate) scoring_tiles: Vec<ScoringTile>, // One for each round
+    pub(crate) bonus_tiles: Vec<BonusTile>,     // Bonus tiles not held by any player
+    pub(crate) book_actions: Vec<BookAction>,
+    pub(crate) book_actions_taken: Vec<usize>, // Indices in book_actions, this round
+    pub(crate) round: usize,                   // Zero-indexed
+    pub(crate) turn_order: Vec<PlayerId>,
+    pub(crate) awards: Vec<ScoreAward>, // VP awarded by scoring tiles, oldest first
+}
+
+/// VP a player got from the scoring tile of a round, and what for.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ScoreAward {
+    pub round: usize,
+    pub player_id: PlayerId,
+    pub event: ScoringEvent,
+    pub vp: VP,
+}
+
+impl GameState {
+    pub fn new(factions: Vec<Faction>, map: map::Map) -> Self {
+        let turn_order = (0..factions.len()).collect();
+
+        GameState {
+            factions,
+            map,
+            bridges: Vec::new(),
+            scoring_tiles: Vec::new(),
+            bonus_tiles: Vec::new(),
+            book_actions: Vec::new(),
+            book_actions_taken: Vec::new(),
+            round: 0,
+            turn_order,
+            awards: Vec::new(),
+        }
+    }
+
+    pub fn factions(&self) -> &Vec<Faction> {
+        &self.factions
+    }
+
+    pub fn map(&self) -> &map::Map {
+        &self.map
+    }
+
+    pub fn bridges(&self) -> &Vec<map::Bridge> {
+        &self.bridges
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn turn_order(&self) -> &Vec<PlayerId> {
+        &self.turn_order
+    }
+
+    pub fn bonus_tiles(&self) -> &Vec<BonusTile> {
+        &self.bonus_tiles
+    }
+
+    pub fn book_actions(&self) -> &Vec<BookAction> {
+        &self.book_actions
+    }
+
+    /// Whether the book action has been taken by any player this round.
+    pub fn book_action_taken(&self, idx: usize) -> bool {
+        self.book_actions_taken.contains(&idx)
+    }
+
+    pub fn scoring_tiles(&self) -> &Vec<ScoringTile> {
+        &self.scoring_tiles
+    }
+
+    /// The scoring tile of the current round, if the game was set up with scoring tiles.
+    pub fn scoring_tile(&self) -> Option<&ScoringTile> {
+        self.scoring_tiles.get(self.round)
+    }
+
+    pub fn awards(&self) -> &Vec<ScoreAward> {
+        &self.awards
+    }
+
+    /// Awards the player VP for the event if it meets the goal of the round's scoring tile.
+    pub(crate) fn score_event(&mut self, player_id: PlayerId, event: ScoringEvent) {
+        let Some(tile) = self.scoring_tile() else {
+            return;
+        };
+
+        let vp = tile.left_side.vp_for(&event);
+        if vp.0 > 0 {
+            let vp = VP(vp.0 + self.factions[player_id].race().award_bonus().0);
+            self.factions[player_id].gain_vp(vp);
+            self.awards.push(ScoreAward {
+                round: self.round,
+                player_id,
+                event,
+                vp,
+            });
+        }
+    }
+
+    pub fn is_last_round(&self) -> bool {
+        self.round + 1 >= NUM_ROUNDS
+    }
+}
@@ -0,0 +1,1208 @@
+use crate::bonustile::BonusTile;
+use crate::bookaction::{self, BookActionEffect};
+use crate::building::Building;
+use crate::common::{Color, Discipline, VP};
+use crate::error::AoiError;
+use crate::leech::{self, LeechOffer, LeechQueue};
+use crate::map::{self, Bridge, Terrain};
+use crate::power::Conversion;
+use crate::resources::{Books, Resources};
+use crate::scoringtile::ScoringEvent;
+use crate::{reachability, terraform, Result};
+
+use crate::gamephase::cleanupphase::CleanupPhase;
+use crate::gamephase::{GameState, PlayerId};
+
+#[derive(Clone)]
+pub struct ActionPhase {
+    state: GameState,
+    current: usize,              // Index in the turn order of the player to move
+    passed: Vec<PlayerId>,       // In the order the players passed
+    main_action_done: bool,      // Whether the player to move has taken the main action of the turn
+    turn_leech: Vec<LeechOffer>, // Offers caused by this turn, made when the turn ends
+    leech: LeechQueue,
+    undo: Vec<TurnSnapshot>, // Taken before every action of the current turn, oldest first
+    redo: Vec<TurnSnapshot>, // Taken before every undo, cleared by new actions
+}
+
+/// Everything the actions within a turn may change, so that they can be taken back
+/// until the turn ends.
+#[derive(Clone)]
+struct TurnSnapshot {
+    state: GameState,
+    main_action_done: bool,
+    turn_leech: Vec<LeechOffer>,
+}
+
+impl ActionPhase {
+    pub fn new(state: GameState) -> ActionPhase {
+        ActionPhase {
+            state,
+            current: 0,
+            passed: Vec::new(),
+            main_action_done: false,
+            turn_leech: Vec::new(),
+            leech: LeechQueue::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn player_to_move(&self) -> Result<PlayerId> {
+        if self.passed.len() == self.state.factions.len() {
+            Err(AoiError::AllPlayersPassed)
+        } else {
+            Ok(self.state.turn_order[self.current])
+        }
+    }
+
+    pub fn has_passed(&self, player_id: PlayerId) -> bool {
+        self.passed.contains(&player_id)
+    }
+
+    /// Builds a workshop on a reachable hex. Hexes not of the player's color are
+    /// terraformed first.
+    pub fn build(&mut self, player_id: PlayerId, pos: map::Pos) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+        if !map::inside_bounds(pos) {
+            return Err(AoiError::OutsideMap { pos });
+        }
+
+        let before = self.snapshot();
+        let map = &mut self.state.map;
+        let faction = &mut self.state.factions[player_id];
+
+        let hex = &map[pos.0][pos.1];
+        if hex.building.is_some() {
+            return Err(AoiError::HexOccupied { pos });
+        }
+        let hex_color = match hex.terrain {
+            Terrain::Land(color) => color,
+            Terrain::Water => return Err(AoiError::WaterHex { pos }),
+        };
+
+        let own_buildings = map::buildings_of(map, player_id);
+        if !reachability::reachable_by(map, &own_buildings, faction).contains(pos) {
+            return Err(AoiError::NotReachable { pos });
+        }
+
+        let home_color = faction.get_color();
+        let total_cost = Resources::from(terraform::terraform_cost(faction, hex_color, home_color))
+            + &Building::Workshop.cost().unwrap();
+        if !faction.can_afford(&total_cost) {
+            return Err(AoiError::NotEnoughResources {
+                required: total_cost,
+                available: faction.resources(),
+            });
+        }
+
+        let spades = if terraform::spade_distance(hex_color, home_color) > 0 {
+            terraform::terraform(map, pos, faction, home_color)?
+        } else {
+            0
+        };
+        faction.build_workshop()?;
+        map[pos.0][pos.1].building = Some((player_id, Building::Workshop));
+        self.main_action_done = true;
+        self.turn_leech = leech::leech_offers(map, &self.state.factions, player_id, pos);
+        if spades > 0 {
+            self.state
+                .score_event(player_id, ScoringEvent::Dug { spades });
+        }
+        self.state.score_event(
+            player_id,
+            ScoringEvent::Built {
+                building: Building::Workshop,
+            },
+        );
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Terraforms a reachable hex into any color without building on it.
+    pub fn terraform(&mut self, player_id: PlayerId, pos: map::Pos, to: Color) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+        if !map::inside_bounds(pos) {
+            return Err(AoiError::OutsideMap { pos });
+        }
+
+        let before = self.snapshot();
+        let map = &mut self.state.map;
+        let faction = &mut self.state.factions[player_id];
+        let own_buildings = map::buildings_of(map, player_id);
+        if !reachability::reachable_by(map, &own_buildings, faction).contains(pos) {
+            return Err(AoiError::NotReachable { pos });
+        }
+
+        let spades = terraform::terraform(map, pos, faction, to)?;
+        self.main_action_done = true;
+        self.state
+            .score_event(player_id, ScoringEvent::Dug { spades });
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Upgrades one of the player's buildings following the upgrade graph.
+    pub fn upgrade(&mut self, player_id: PlayerId, pos: map::Pos, to: Building) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+        if !map::inside_bounds(pos) {
+            return Err(AoiError::OutsideMap { pos });
+        }
+
+        let before = self.snapshot();
+        let map = &mut self.state.map;
+        let from = match map[pos.0][pos.1].building {
+            Some((owner, building)) if owner == player_id => building,
+            _ => return Err(AoiError::NoOwnBuilding { player_id, pos }),
+        };
+
+        let has_neighbor = map::has_other_neighbor(map, pos, player_id);
+        self.state.factions[player_id].upgrade(from, to, has_neighbor)?;
+        map[pos.0][pos.1].building = Some((player_id, to));
+        self.main_action_done = true;
+        self.turn_leech = leech::leech_offers(map, &self.state.factions, player_id, pos);
+        self.state
+            .score_event(player_id, ScoringEvent::Built { building: to });
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Builds a bridge across water from a hex with one of the player's buildings, paid
+    /// with power.
+    pub fn build_bridge(
+        &mut self,
+        player_id: PlayerId,
+        from: map::Pos,
+        to: map::Pos,
+    ) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+        let ends = (from, to);
+        if !map::can_bridge(&self.state.map, ends) {
+            return Err(AoiError::InvalidBridge { ends });
+        }
+        if !matches!(self.state.map[from.0][from.1].building, Some((owner, _)) if owner == player_id)
+        {
+            return Err(AoiError::NoOwnBuilding {
+                player_id,
+                pos: from,
+            });
+        }
+        if self
+            .state
+            .bridges
+            .iter()
+            .any(|br| br.ends == (from, to) || br.ends == (to, from))
+        {
+            return Err(AoiError::BridgeExists { ends });
+        }
+        let built = self
+            .state
+            .bridges
+            .iter()
+            .filter(|br| br.player_id == player_id)
+            .count();
+        if built >= map::MAX_BRIDGES {
+            return Err(AoiError::NoBridgesLeft { player_id });
+        }
+
+        let before = self.snapshot();
+        self.state.factions[player_id].pay(&Resources::from(map::BRIDGE_COST))?;
+        self.state.bridges.push(Bridge { player_id, ends });
+        self.main_action_done = true;
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Advances sailing as the main action of the turn.
+    pub fn advance_sailing(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+
+        let before = self.snapshot();
+        self.state.factions[player_id].advance_sailing()?;
+        self.main_action_done = true;
+        self.state
+            .score_event(player_id, ScoringEvent::AdvancedSailing);
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Advances digging as the main action of the turn.
+    pub fn advance_digging(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+
+        let before = self.snapshot();
+        self.state.factions[player_id].advance_digging()?;
+        self.main_action_done = true;
+        self.state
+            .score_event(player_id, ScoringEvent::AdvancedDigging);
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Burns and spends power. Conversions may be made any number of times during the
+    /// player's turn.
+    pub fn convert_power(&mut self, player_id: PlayerId, conversion: &Conversion) -> Result<()> {
+        self.ensure_turn(player_id)?;
+
+        let before = self.snapshot();
+        self.state.factions[player_id].convert_power(conversion)?;
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Takes one of the book actions as the main action of the turn, paying with books of
+    /// the picked colors. Advancing a discipline needs `disc`, while upgrading a workshop
+    /// and digging need `pos`. Each book action may only be taken once per round.
+    pub fn book_action(
+        &mut self,
+        player_id: PlayerId,
+        idx: usize,
+        payment: &[(Discipline, Books)],
+        disc: Option<Discipline>,
+        pos: Option<map::Pos>,
+    ) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+        let action = *self
+            .state
+            .book_actions
+            .get(idx)
+            .ok_or(AoiError::NoSuchBookAction { idx })?;
+        if self.state.book_action_taken(idx) {
+            return Err(AoiError::BookActionTaken { idx });
+        }
+
+        let before = self.snapshot();
+        let result = action.payment(payment).and_then(|cost| {
+            self.state.factions[player_id].pay(&cost)?;
+            self.book_effect(player_id, action.effect(), disc, pos)
+        });
+        if let Err(err) = result {
+            self.restore(before);
+            return Err(err);
+        }
+        self.state.book_actions_taken.push(idx);
+        self.main_action_done = true;
+        self.record(before);
+
+        Ok(())
+    }
+
+    /// Takes back the last action of the turn.
+    pub fn undo(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_turn(player_id)?;
+
+        let before = self
+            .undo
+            .pop()
+            .ok_or(AoiError::NothingToUndo { player_id })?;
+        let after = self.restore(before);
+        self.redo.push(after);
+
+        Ok(())
+    }
+
+    /// Takes the last undone action again.
+    pub fn redo(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_turn(player_id)?;
+
+        let after = self
+            .redo
+            .pop()
+            .ok_or(AoiError::NothingToRedo { player_id })?;
+        let before = self.restore(after);
+        self.undo.push(before);
+
+        Ok(())
+    }
+
+    /// Takes back every action of the turn, leaving the player as the turn started.
+    pub fn abort_turn(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_turn(player_id)?;
+
+        if !self.undo.is_empty() {
+            let start = self.undo.swap_remove(0);
+            self.restore(start);
+            self.undo.clear();
+            self.redo.clear();
+        }
+
+        Ok(())
+    }
+
+    /// A copy without the snapshots of the turn, for trying out actions other than undo
+    /// and redo.
+    pub(crate) fn without_history(&self) -> ActionPhase {
+        ActionPhase {
+            state: self.state.clone(),
+            current: self.current,
+            passed: self.passed.clone(),
+            main_action_done: self.main_action_done,
+            turn_leech: self.turn_leech.clone(),
+            leech: self.leech.clone(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Ends the turn of a player that has taken a main action.
+    pub fn end_turn(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_turn(player_id)?;
+        if !self.main_action_done {
+            return Err(AoiError::MainActionRequired { player_id });
+        }
+
+        self.main_action_done = false;
+        self.leech.push(std::mem::take(&mut self.turn_leech));
+        self.next_turn();
+
+        Ok(())
+    }
+
+    /// Power offered to players after their neighbors built, waiting for a decision.
+    pub fn pending_leech(&self) -> &Vec<LeechOffer> {
+        self.leech.pending()
+    }
+
+    /// Accepts or declines the oldest power offer made to the player. The game does not
+    /// continue until all offers have been decided on.
+    pub fn leech(&mut self, player_id: PlayerId, accept: bool) -> Result<()> {
+        self.leech
+            .decide(player_id, accept, &mut self.state.factions)
+            .map(|_| ())
+    }
+
+    /// Passes for the rest of the round. Unless it is the last round, the player returns
+    /// the held bonus tile and takes one of the available ones.
+    pub fn pass(&mut self, player_id: PlayerId, bonus_tile: Option<BonusTile>) -> Result<()> {
+        self.ensure_main_action(player_id)?;
+
+        match bonus_tile {
+            Some(_) if self.state.is_last_round() => {
+                return Err(AoiError::NoBonusTileInLastRound);
+            }
+            Some(tile) => {
+                let idx = self
+                    .state
+                    .bonus_tiles
+                    .iter()
+                    .position(|&t| t == tile)
+                    .ok_or(AoiError::BonusTileUnavailable { tile })?;
+                self.state.bonus_tiles.remove(idx);
+
+                let returned = self.state.factions[player_id].set_bonus_tile(Some(tile));
+                self.state.bonus_tiles.extend(returned);
+            }
+            None => {
+                if !self.state.is_last_round() && !self.state.bonus_tiles.is_empty() {
+                    return Err(AoiError::BonusTileRequired);
+                }
+            }
+        }
+
+        self.passed.push(player_id);
+        self.next_turn();
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<CleanupPhase> {
+        if self.passed.len() == self.state.factions.len() {
+            Ok(CleanupPhase::new(self.state, self.passed))
+        } else {
+            Err(AoiError::NotAllPlayersPassed)
+        }
+    }
+
+    fn ensure_turn(&self, player_id: PlayerId) -> Result<()> {
+        if !self.leech.is_empty() {
+            return Err(AoiError::WaitingForLeech);
+        }
+
+        if self.player_to_move()? == player_id {
+            Ok(())
+        } else {
+            Err(AoiError::NotPlayersTurn { player_id })
+        }
+    }
+
+    fn ensure_main_action(&self, player_id: PlayerId) -> Result<()> {
+        self.ensure_turn(player_id)?;
+        if self.main_action_done {
+            Err(AoiError::MainActionAlreadyTaken { player_id })
+        } else {
+            Ok(())
+        }
+    }
+
+    // May fail after changing the state, which must then be restored
+    fn book_effect(
+        &mut self,
+        player_id: PlayerId,
+        effect: BookActionEffect,
+        disc: Option<Discipline>,
+        pos: Option<map::Pos>,
+    ) -> Result<()> {
+        let target_required = AoiError::BookActionTargetRequired { effect };
+        let map = &mut self.state.map;
+        let faction = &mut self.state.factions[player_id];
+
+        match effect {
+            BookActionEffect::GainPower => {
+                faction.gain_power(bookaction::POWER_GAINED);
+            }
+            BookActionEffect::DiscStep => {
+                let disc = disc.ok_or(target_required)?;
+                let level = faction.disc_level(disc);
+                let steps = faction.incr_disc(disc, bookaction::DISC_STEPS) - level;
+                if steps > 0 {
+                    self.state
+                        .score_event(player_id, ScoringEvent::AdvancedDiscipline { disc, steps });
+                }
+            }
+            BookActionEffect::GainCoins => {
+                faction.gain(&Resources::from(bookaction::COINS_GAINED));
+            }
+            BookActionEffect::UpgradeToGuild => {
+                let pos = pos.ok_or(target_required)?;
+                if !map::inside_bounds(pos) {
+                    return Err(AoiError::OutsideMap { pos });
+                }
+                let from = match map[pos.0][pos.1].building {
+                    Some((owner, building)) if owner == player_id => building,
+                    _ => return Err(AoiError::NoOwnBuilding { player_id, pos }),
+                };
+
+                faction.upgrade_for_free(from, Building::Guild)?;
+                map[pos.0][pos.1].building = Some((player_id, Building::Guild));
+                self.turn_leech = leech::leech_offers(map, &self.state.factions, player_id, pos);
+                self.state.score_event(
+                    player_id,
+                    ScoringEvent::Built {
+                        building: Building::Guild,
+                    },
+                );
+            }
+            BookActionEffect::PointsPerGuild => {
+                let guilds = map::buildings_of(map, player_id)
+                    .into_iter()
+                    .filter(|&(r, c)| map[r][c].building == Some((player_id, Building::Guild)))
+                    .count() as u32;
+                faction.gain_vp(VP(guilds * bookaction::VP_PER_GUILD));
+            }
+            BookActionEffect::Spades => {
+                let pos = pos.ok_or(target_required)?;
+                if !map::inside_bounds(pos) {
+                    return Err(AoiError::OutsideMap { pos });
+                }
+                let own_buildings = map::buildings_of(map, player_id);
+                if !reachability::reachable_by(map, &own_buildings, faction).contains(pos) {
+                    return Err(AoiError::NotReachable { pos });
+                }
+
+                let spades = terraform::dig(map, pos, faction, bookaction::SPADES)?;
+                self.state
+                    .score_event(player_id, ScoringEvent::Dug { spades });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn snapshot(&self) -> TurnSnapshot {
+        TurnSnapshot {
+            state: self.state.clone(),
+            main_action_done: self.main_action_done,
+            turn_leech: self.turn_leech.clone(),
+        }
+    }
+
+    // Makes an action taken since the snapshot undoable
+    fn record(&mut self, before: TurnSnapshot) {
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    // Returns a snapshot of the state that was replaced
+    fn restore(&mut self, snapshot: TurnSnapshot) -> TurnSnapshot {
+        TurnSnapshot {
+            state: std::mem::replace(&mut self.state, snapshot.state),
+            main_action_done: std::mem::replace(
+                &mut self.main_action_done,
+                snapshot.main_action_done,
+            ),
+            turn_leech: std::mem::replace(&mut self.turn_leech, snapshot.turn_leech),
+        }
+    }
+
+    // The actions of a turn can't be taken back once the next player is to move
+    fn next_turn(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        if self.passed.len() == self.state.factions.len() {
+            return;
+        }
+
+        let num_players = self.state.turn_order.len();
+        loop {
+            self.current = (self.current + 1) % num_players;
+            if !self.has_passed(self.state.turn_order[self.current]) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bookaction::BookAction;
+    use crate::common::{Color, VP};
+    use crate::faction::Faction;
+    use crate::gamephase::ScoreAward;
+    use crate::map;
+    use crate::race::Race;
+    use crate::resources::{Coins, Power, Scholars, Tools};
+    use crate::scoringtile::{self, LeftSideGoal};
+
+    use parameterized::parameterized;
+
+    fn create_test_action_phase(num_players: usize) -> ActionPhase {
+        let factions = (0..num_players)
+            .map(|_| Faction::new(&Race::Raceless, &Color::Colorless))
+            .collect();
+
+        ActionPhase::new(GameState::new(factions, map::open_map(map::MapId::Debug)))
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn players_move_in_turn_order(num_players: usize) {
+        let mut phase = create_test_action_phase(num_players);
+
+        for player_id in 0..num_players {
+            assert_eq!(phase.player_to_move().unwrap(), player_id);
+            phase.pass(player_id, None).unwrap();
+        }
+
+        assert!(phase.player_to_move().is_err());
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn only_player_to_move_may_pass(num_players: usize) {
+        let mut phase = create_test_action_phase(num_players);
+
+        for player_id in 1..num_players {
+            assert!(phase.pass(player_id, None).is_err());
+        }
+        assert!(!phase.has_passed(1));
+    }
+
+    #[test]
+    fn passed_players_are_skipped() {
+        let mut phase = create_test_action_phase(3);
+        phase.state.turn_order = vec![2, 0, 1];
+
+        phase.pass(2, None).unwrap();
+        assert_eq!(phase.player_to_move().unwrap(), 0);
+        phase.pass(0, None).unwrap();
+        assert_eq!(phase.player_to_move().unwrap(), 1);
+    }
+
+    fn create_yellow_action_phase() -> ActionPhase {
+        let factions = vec![
+            Faction::new(&Race::Raceless, &Color::Yellow),
+            Faction::new(&Race::Raceless, &Color::Brown),
+        ];
+        let mut phase =
+            ActionPhase::new(GameState::new(factions, map::open_map(map::MapId::Debug)));
+        for (player_id, pos) in [(0, (1, 1)), (1, (2, 1))] {
+            phase.state.map[pos.0][pos.1].building = Some((player_id, Building::Workshop));
+            phase.state.factions[player_id]
+                .take_from_supply(Building::Workshop)
+                .unwrap();
+        }
+
+        phase
+    }
+
+    #[test]
+    fn build_workshop_next_to_own_building() {
+        let mut phase = create_yellow_action_phase();
+
+        phase.build(0, (1, 2)).unwrap();
+
+        assert_eq!(
+            phase.state.map[1][2].building,
+            Some((0, Building::Workshop))
+        );
+        assert_eq!(
+            phase.state.factions[0]
+                .supply()
+                .remaining(Building::Workshop),
+            7
+        );
+    }
+
+    #[test]
+    fn build_terraforms_to_home_color() {
+        let mut phase = create_yellow_action_phase();
+
+        phase.state.factions[0].gain(&Resources::from(Tools(1)));
+
+        // Brown is one spade (three tools) away from yellow and the workshop costs one tool
+        phase.build(0, (2, 2)).unwrap();
+
+        assert_eq!(phase.state.map[2][2].terrain, Terrain::Land(Color::Yellow));
+        assert_eq!(phase.state.factions[0].tools(), Tools(0));
+    }
+
+    #[test]
+    fn failed_build_changes_nothing() {
+        let mut phase = create_yellow_action_phase();
+
+        // Not enough tools for both the spade and the workshop
+        assert!(phase.build(0, (2, 2)).is_err());
+
+        assert_eq!(phase.state.map[2][2].terrain, Terrain::Land(Color::Brown));
+        assert_eq!(phase.state.factions[0].tools(), Tools(3));
+        assert!(phase.end_turn(0).is_err());
+    }
+
+    #[test]
+    fn cant_build_out_of_reach() {
+        let mut phase = create_yellow_action_phase();
+
+        assert_eq!(
+            phase.build(0, (1, 8)),
+            Err(AoiError::NotReachable { pos: (1, 8) })
+        );
+        assert_eq!(
+            phase.build(0, (1, 1)),
+            Err(AoiError::HexOccupied { pos: (1, 1) })
+        );
+        assert_eq!(
+            phase.build(0, (0, 1)),
+            Err(AoiError::WaterHex { pos: (0, 1) })
+        );
+    }
+
+    #[test]
+    fn one_main_action_per_turn() {
+        let mut phase = create_yellow_action_phase();
+
+        assert_eq!(
+            phase.end_turn(0),
+            Err(AoiError::MainActionRequired { player_id: 0 })
+        );
+        phase.build(0, (1, 2)).unwrap();
+        assert_eq!(
+            phase.build(0, (1, 3)),
+            Err(AoiError::MainActionAlreadyTaken { player_id: 0 })
+        );
+        assert!(phase.pass(0, None).is_err());
+        phase.end_turn(0).unwrap();
+
+        assert_eq!(phase.player_to_move().unwrap(), 1);
+    }
+
+    #[test]
+    fn upgrade_own_building() {
+        let mut phase = create_yellow_action_phase();
+
+        assert!(phase.upgrade(0, (2, 1), Building::Guild).is_err()); // Other player's building
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+
+        assert_eq!(phase.state.map[1][1].building, Some((0, Building::Guild)));
+        // Cheaper guild since the other player's workshop is adjacent
+        let json = serde_json::to_value(&phase.state.factions[0]).unwrap();
+        assert_eq!(json["coins"], 12);
+    }
+
+    fn with_scoring_tile(mut phase: ActionPhase, goal: LeftSideGoal) -> ActionPhase {
+        let tile = scoringtile::all_scoring_tiles()
+            .into_iter()
+            .find(|tile| tile.left_side.goal == goal)
+            .unwrap();
+        phase.state.scoring_tiles = vec![tile];
+
+        phase
+    }
+
+    #[test]
+    fn scoring_tile_awards_vp_for_goal() {
+        let mut phase = with_scoring_tile(create_yellow_action_phase(), LeftSideGoal::Dig);
+        phase.state.factions[0].gain(&Resources::from(Tools(1)));
+
+        phase.build(0, (2, 2)).unwrap();
+
+        assert_eq!(phase.state.factions[0].vp(), VP(22));
+        assert_eq!(
+            phase.state.awards(),
+            &vec![ScoreAward {
+                round: 0,
+                player_id: 0,
+                event: ScoringEvent::Dug { spades: 1 },
+                vp: VP(2),
+            }]
+        );
+    }
+
+    #[test]
+    fn other_goals_award_nothing() {
+        let mut phase = with_scoring_tile(create_yellow_action_phase(), LeftSideGoal::BuildGuild);
+
+        phase.build(0, (1, 2)).unwrap();
+
+        assert_eq!(phase.state.factions[0].vp(), VP(20));
+        assert!(phase.state.awards().is_empty());
+    }
+
+    #[test]
+    fn undo_takes_back_award() {
+        let mut phase = with_scoring_tile(create_yellow_action_phase(), LeftSideGoal::BuildGuild);
+
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+        assert_eq!(phase.state.factions[0].vp(), VP(23));
+        phase.undo(0).unwrap();
+
+        assert_eq!(phase.state.factions[0].vp(), VP(20));
+        assert!(phase.state.awards().is_empty());
+    }
+
+    #[test]
+    fn neighbors_are_offered_power_when_turn_ends() {
+        let mut phase = create_yellow_action_phase();
+
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+        assert!(phase.pending_leech().is_empty());
+        phase.end_turn(0).unwrap();
+
+        assert_eq!(phase.pending_leech().len(), 1);
+        assert_eq!(phase.pending_leech()[0].player_id, 1);
+        assert_eq!(phase.pending_leech()[0].power, 1);
+    }
+
+    #[test]
+    fn game_waits_for_leech_decisions() {
+        let mut phase = create_yellow_action_phase();
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+        phase.end_turn(0).unwrap();
+
+        assert_eq!(phase.pass(1, None), Err(AoiError::WaitingForLeech));
+        assert_eq!(
+            phase.leech(0, true),
+            Err(AoiError::NoLeechOffer { player_id: 0 })
+        );
+        phase.leech(1, true).unwrap();
+
+        assert!(phase.pending_leech().is_empty());
+        assert_eq!(phase.state.factions[1].power().amount(2), 8);
+        assert!(phase.pass(1, None).is_ok());
+    }
+
+    #[test]
+    fn cant_finish_before_all_have_passed() {
+        let mut phase = create_test_action_phase(2);
+
+        phase.pass(0, None).unwrap();
+
+        assert!(phase.finish().is_err());
+    }
+
+    #[test]
+    fn power_is_converted_on_own_turn_only() {
+        let mut phase = create_yellow_action_phase();
+        let conversion = Conversion {
+            burn: 1,
+            coins: Coins(1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            phase.convert_power(1, &conversion),
+            Err(AoiError::NotPlayersTurn { player_id: 1 })
+        );
+        phase.convert_power(0, &conversion).unwrap();
+        phase.convert_power(0, &conversion).unwrap();
+
+        assert_eq!(phase.state.factions[0].power().amount(2), 3);
+        assert_eq!(phase.player_to_move().unwrap(), 0);
+    }
+
+    #[test]
+    fn actions_are_undone_step_by_step() {
+        let mut phase = create_yellow_action_phase();
+        let conversion = Conversion {
+            burn: 1,
+            coins: Coins(1),
+            ..Default::default()
+        };
+
+        phase.convert_power(0, &conversion).unwrap();
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+
+        phase.undo(0).unwrap();
+        assert_eq!(
+            phase.state.map[1][1].building,
+            Some((0, Building::Workshop))
+        );
+        assert_eq!(phase.state.factions[0].power().amount(2), 5);
+        assert!(phase.end_turn(0).is_err()); // The main action was undone
+
+        phase.undo(0).unwrap();
+        assert_eq!(phase.state.factions[0].power().amount(2), 7);
+        assert_eq!(phase.undo(0), Err(AoiError::NothingToUndo { player_id: 0 }));
+    }
+
+    #[test]
+    fn undone_actions_are_redone() {
+        let mut phase = create_yellow_action_phase();
+
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+        phase.undo(0).unwrap();
+        phase.redo(0).unwrap();
+
+        assert_eq!(phase.state.map[1][1].building, Some((0, Building::Guild)));
+        assert_eq!(phase.redo(0), Err(AoiError::NothingToRedo { player_id: 0 }));
+        phase.end_turn(0).unwrap();
+        assert_eq!(phase.pending_leech().len(), 1);
+    }
+
+    #[test]
+    fn new_action_clears_redo() {
+        let mut phase = create_yellow_action_phase();
+
+        phase.build(0, (1, 2)).unwrap();
+        phase.undo(0).unwrap();
+        phase.upgrade(0, (1, 1), Building::Guild).unwrap();
+
+        assert!(!phase.can_redo());
+        assert_eq!(phase.state.map[1][2].building, None);
+    }
+
+    #[test]
+    fn abort_returns_to_turn_start() {
+        let mut phase = create_yellow_action_phase();
+        let conversion = Conversion {
+            burn: 1,
+            coins: Coins(1),
+            ..Default::default()
+        };
+
+        phase.convert_power(0, &conversion).unwrap();
+        phase.build(0, (1, 2)).unwrap();
+        phase.abort_turn(0).unwrap();
+
+        assert_eq!(phase.state.map[1][2].building, None);
+        assert_eq!(phase.state.factions[0].power().amount(2), 7);
+        assert!(!phase.can_undo());
+        assert!(!phase.can_redo());
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn ended_turn_cant_be_undone(num_players: usize) {
+        let mut phase = create_test_action_phase(num_players);
+        let conversion = Conversion {
+            burn: 1,
+            coins: Coins(1),
+            ..Default::default()
+        };
+
+        for player_id in 0..num_players {
+            phase.convert_power(player_id, &conversion).unwrap();
+            phase.pass(player_id, None).unwrap();
+            assert!(!phase.can_undo());
+        }
+        assert!(phase.undo(0).is_err());
+    }
+
+    #[test]
+    fn only_player_to_move_may_undo() {
+        let mut phase = create_yellow_action_phase();
+        phase.build(0, (1, 2)).unwrap();
+
+        assert_eq!(
+            phase.undo(1),
+            Err(AoiError::NotPlayersTurn { player_id: 1 })
+        );
+        assert!(phase.can_undo());
+    }
+
+    #[test]
+    fn passing_swaps_bonus_tile() {
+        let mut phase = create_test_action_phase(2);
+        phase.state.bonus_tiles = vec![BonusTile::BonCoins, BonusTile::BonSpade];
+        phase.state.factions[0].set_bonus_tile(Some(BonusTile::BonScholar));
+
+        phase.pass(0, Some(BonusTile::BonSpade)).unwrap();
+
+        assert_eq!(
+            phase.state.factions[0].bonus_tile(),
+            Some(BonusTile::BonSpade)
+        );
+        assert_eq!(
+            phase.state.bonus_tiles,
+            vec![BonusTile::BonCoins, BonusTile::BonScholar]
+        );
+    }
+
+    #[test]
+    fn must_take_available_bonus_tile_when_passing() {
+        let mut phase = create_test_action_phase(2);
+        phase.state.bonus_tiles = vec![BonusTile::BonCoins];
+
+        assert!(phase.pass(0, None).is_err());
+        assert!(phase.pass(0, Some(BonusTile::BonSpade)).is_err());
+        assert!(phase.pass(0, Some(BonusTile::BonCoins)).is_ok());
+    }
+
+    #[test]
+    fn no_bonus_tile_taken_in_last_round() {
+        let mut phase = create_test_action_phase(2);
+        phase.state.bonus_tiles = vec![BonusTile::BonCoins];
+        phase.state.round = crate::gamephase::NUM_ROUNDS - 1;
+
+        assert!(phase.pass(0, Some(BonusTile::BonCoins)).is_err());
+        assert!(phase.pass(0, None).is_ok());
+    }
+
+    fn with_book_actions(mut phase: ActionPhase) -> ActionPhase {
+        phase.state.book_actions = vec![
+            BookAction::new(Books(2), BookActionEffect::GainCoins),
+            BookAction::new(Books(3), BookActionEffect::Spades),
+            BookAction::new(Books(2), BookActionEffect::UpgradeToGuild),
+        ];
+        for faction in &mut phase.state.factions {
+            faction.gain(
+                &(Resources::from((Discipline::Law, Books(2)))
+                    + &Resources::from((Discipline::Medicine, Books(2)))),
+            );
+        }
+
+        phase
+    }
+
+    #[test]
+    fn book_action_is_paid_with_picked_colors() {
+        let mut phase = with_book_actions(create_yellow_action_phase());
+        let payment = [
+            (Discipline::Law, Books(1)),
+            (Discipline::Medicine, Books(1)),
+        ];
+
+        phase.book_action(0, 0, &payment, None, None).unwrap();
+
+        let faction = &phase.state.factions[0];
+        assert_eq!(faction.books(Discipline::Law), Books(1));
+        assert_eq!(faction.books(Discipline::Medicine), Books(1));
+        assert_eq!(faction.resources().get::<Coins>(), Coins(21));
+        assert_eq!(
+            phase.book_action(0, 1, &[], None, None),
+            Err(AoiError::MainActionAlreadyTaken { player_id: 0 })
+        );
+    }
+
+    #[test]
+    fn book_action_is_taken_once_per_round() {
+        let mut phase = with_book_actions(create_yellow_action_phase());
+        let payment = [(Discipline::Law, Books(2))];
+
+        phase.book_action(0, 0, &payment, None, None).unwrap();
+        phase.end_turn(0).unwrap();
+
+        assert_eq!(
+            phase.book_action(1, 0, &payment, None, None),
+            Err(AoiError::BookActionTaken { idx: 0 })
+        );
+        assert_eq!(
+            phase.book_action(1, 3, &payment, None, None),
+            Err(AoiError::NoSuchBookAction { idx: 3 })
+        );
+    }
+
+    #[test]
+    fn failed_book_action_changes_nothing() {
+        let mut phase = with_book_actions(create_yellow_action_phase());
+        let payment = [
+            (Discipline::Law, Books(2)),
+            (Discipline::Medicine, Books(1)),
+        ];
+
+        assert_eq!(
+            phase.book_action(0, 1, &payment, None, None),
+            Err(AoiError::BookActionTargetRequired {
+                effect: BookActionEffect::Spades
+            })
+        );
+        assert_eq!(
+            phase.book_action(0, 1, &payment, None, Some((1, 8))),
+            Err(AoiError::NotReachable { pos: (1, 8) })
+        );
+
+        assert_eq!(phase.state.factions[0].books(Discipline::Law), Books(2));
+        assert!(!phase.state.book_action_taken(1));
+        assert!(!phase.can_undo());
+    }
+
+    #[test]
+    fn book_action_digs_and_upgrades() {
+        let mut phase = with_book_actions(create_yellow_action_phase());
+
+        phase
+            .book_action(
+                0,
+                1,
+                &[
+                    (Discipline::Law, Books(2)),
+                    (Discipline::Medicine, Books(1)),
+                ],
+                None,
+                Some((2, 2)),
+            )
+            .unwrap();
+        phase.end_turn(0).unwrap();
+        phase
+            .book_action(1, 2, &[(Discipline::Law, Books(2))], None, Some((2, 1)))
+            .unwrap();
+
+        assert_eq!(phase.state.map[2][2].terrain, Terrain::Land(Color::Yellow));
+        assert_eq!(phase.state.map[2][1].building, Some((1, Building::Guild)));
+        assert_eq!(
+            phase.state.factions[1].resources().get::<Coins>(),
+            Coins(15)
+        );
+    }
+
+    #[test]
+    fn undo_takes_back_book_action() {
+        let mut phase = with_book_actions(create_yellow_action_phase());
+
+        phase
+            .book_action(0, 0, &[(Discipline::Law, Books(2))], None, None)
+            .unwrap();
+        phase.undo(0).unwrap();
+
+        assert_eq!(phase.state.factions[0].books(Discipline::Law), Books(2));
+        assert!(!phase.state.book_action_taken(0));
+    }
+
+    #[test]
+    fn terraform_without_building() {
+        let mut phase = with_scoring_tile(create_yellow_action_phase(), LeftSideGoal::Dig);
+
+        assert_eq!(
+            phase.terraform(0, (1, 8), Color::Red),
+            Err(AoiError::NotReachable { pos: (1, 8) })
+        );
+        // Yellow is one spade from brown
+        phase.terraform(0, (2, 2), Color::Yellow).unwrap();
+
+        assert_eq!(phase.state.map[2][2].terrain, Terrain::Land(Color::Yellow));
+        assert_eq!(phase.state.map[2][2].building, None);
+        assert_eq!(phase.state.factions[0].tools(), Tools(0));
+        assert_eq!(phase.state.factions[0].vp(), VP(22));
+        assert!(phase.end_turn(0).is_ok());
+    }
+
+    #[test]
+    fn sailing_advance_scores_goal() {
+        let mut phase = with_scoring_tile(
+            create_yellow_action_phase(),
+            LeftSideGoal::AdvanceSailingOrDigging,
+        );
+        phase.state.factions[0].gain(&Resources::from(Scholars(1)));
+
+        phase.advance_sailing(0).unwrap();
+
+        assert_eq!(phase.state.factions[0].sailing_level(), 1);
+        assert_eq!(phase.state.factions[0].vp(), VP(23));
+        assert_eq!(
+            phase.advance_digging(0),
+            Err(AoiError::MainActionAlreadyTaken { player_id: 0 })
+        );
+    }
+
+    #[test]
+    fn undo_takes_back_digging_advance() {
+        let mut phase = with_scoring_tile(
+            create_yellow_action_phase(),
+            LeftSideGoal::AdvanceSailingOrDigging,
+        );
+        phase.state.factions[0].gain(&Resources::from(Scholars(1)));
+
+        phase.advance_digging(0).unwrap();
+        assert_eq!(phase.state.factions[0].digging_cost(), Tools(2));
+        assert_eq!(phase.state.factions[0].vp(), VP(23));
+        phase.undo(0).unwrap();
+
+        assert_eq!(phase.state.factions[0].digging_cost(), Tools(3));
+        assert_eq!(phase.state.factions[0].vp(), VP(20));
+        assert!(phase.state.awards().is_empty());
+    }
+
+    #[test]
+    fn bridge_joins_buildings_across_water() {
+        let mut phase = create_yellow_action_phase();
+        phase.state.map[1][5].building = Some((0, Building::Workshop));
+        // Fills bowl 3 with just enough for the bridge
+        phase.state.factions[0].gain(&Resources::from(Power(8)));
+
+        assert_eq!(
+            phase.build_bridge(0, (1, 4), (1, 6)),
+            Err(AoiError::InvalidBridge {
+                ends: ((1, 4), (1, 6))
+            })
+        );
+        assert_eq!(
+            phase.build_bridge(0, (1, 7), (1, 5)),
+            Err(AoiError::NoOwnBuilding {
+                player_id: 0,
+                pos: (1, 7)
+            })
+        );
+        phase.build_bridge(0, (1, 5), (1, 7)).unwrap();
+
+        assert_eq!(
+            phase.state.bridges(),
+            &vec![Bridge {
+                player_id: 0,
+                ends: ((1, 5), (1, 7))
+            }]
+        );
+        assert_eq!(phase.state.factions[0].resources().get::<Power>(), Power(0));
+        assert!(phase.end_turn(0).is_ok());
+    }
+
+    #[test]
+    fn undo_takes_back_bridge() {
+        let mut phase = create_yellow_action_phase();
+        phase.state.map[1][5].building = Some((0, Building::Workshop));
+        // Fills bowl 3 with just enough for the bridge
+        phase.state.factions[0].gain(&Resources::from(Power(8)));
+
+        phase.build_bridge(0, (1, 5), (1, 7)).unwrap();
+        phase.undo(0).unwrap();
+
+        assert!(phase.state.bridges().is_empty());
+        assert_eq!(phase.state.factions[0].resources().get::<Power>(), Power(3));
+    }
+}
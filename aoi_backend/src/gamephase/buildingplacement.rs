@@ -1,77 +1,95 @@
-use crate::error::create_error;
+use crate::building::Building;
+use crate::error::AoiError;
 use crate::faction::Faction;
-use crate::{map, Result};
+use crate::{map, terraform, Result};
 
 use crate::common::Color;
 
 use crate::gamephase::incomephase::IncomePhase;
-use crate::gamephase::PlayerId;
+use crate::gamephase::{GameState, PlayerId};
 
+#[derive(Clone)]
 pub struct BuildingPlacer {
-    factions: Vec<Faction>, // Indices map to player IDs
-    map: map::Map,
+    state: GameState,
     placed: Vec<(PlayerId, map::Pos)>,
 }
 
 impl BuildingPlacer {
     pub fn new(factions: Vec<Faction>, map: map::Map) -> BuildingPlacer {
+        Self::from_state(GameState::new(factions, map))
+    }
+
+    pub fn from_state(state: GameState) -> BuildingPlacer {
         BuildingPlacer {
-            factions,
-            map: map,
+            state,
             placed: Vec::new(),
         }
     }
 
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Places an initial workshop on a hex of the player's color. Factions with setup
+    /// spades may also place on hexes they can dig into their color with them.
     pub fn place(&mut self, player_id: PlayerId, pos: map::Pos) -> Result<()> {
-        // Colorless factions are allowed to place anywhere for the purposes of testing.
-        if !self.valid_placement(player_id, pos) {
-            return Err(create_error("Not a valid hex"));
+        self.check_placement(player_id, pos)?;
+
+        let faction = &mut self.state.factions[player_id];
+        if self.state.map[pos.0][pos.1].terrain != map::Terrain::Land(faction.get_color())
+            && faction.get_color() != Color::Colorless
+        {
+            let spades = terraform::dig(&mut self.state.map, pos, faction, faction.setup_spades())?;
+            faction.use_setup_spades(spades);
         }
-
+        faction.take_from_supply(Building::Workshop)?;
         self.placed.push((player_id, pos));
+        self.state.map[pos.0][pos.1].building = Some((player_id, Building::Workshop));
 
         Ok(())
     }
 
     pub fn valid_placement(&self, player_id: PlayerId, pos: map::Pos) -> bool {
-        let inside_bounds = pos.0 < map::MAP_HEIGHT && pos.1 < map::MAP_WIDTH;
-        if !inside_bounds {
-            return false;
+        self.check_placement(player_id, pos).is_ok()
+    }
+
+    fn check_placement(&self, player_id: PlayerId, pos: map::Pos) -> Result<()> {
+        if !map::inside_bounds(pos) {
+            return Err(AoiError::OutsideMap { pos });
         }
 
-        let already_occupied = self.placed.iter().filter(|(_, p)| pos == *p).count() == 1;
+        let already_occupied = self.placed.iter().any(|(_, p)| pos == *p);
         if already_occupied {
-            return false;
+            return Err(AoiError::HexOccupied { pos });
         }
 
-        let this_players_turn = self
-            .player_to_move()
-            .map(|p| player_id == p)
-            .unwrap_or(false);
-        if !this_players_turn {
-            return false;
+        if self.player_to_move()? != player_id {
+            return Err(AoiError::NotPlayersTurn { player_id });
         }
 
-        let player_color = self.factions[player_id].get_color();
-        let valid_color: bool = match self.map[pos.0][pos.1].terrain {
-            map::Terrain::Land(hex_color) => {
-                if player_color == Color::Colorless {
-                    // Colorless factions are allowed to place anywhere for the purposes of testing.
-                    true
-                } else {
-                    hex_color == player_color
-                }
+        let faction = &self.state.factions[player_id];
+        let player_color = faction.get_color();
+        match self.state.map[pos.0][pos.1].terrain {
+            // Colorless factions are allowed to place anywhere for the purposes of testing.
+            map::Terrain::Land(_) if player_color == Color::Colorless => Ok(()),
+            map::Terrain::Land(hex_color)
+                if terraform::spade_distance(hex_color, player_color) <= faction.setup_spades() =>
+            {
+                Ok(())
             }
-            map::Terrain::Water => false,
-        };
-
-        valid_color
+            map::Terrain::Land(hex_color) => Err(AoiError::WrongColor {
+                pos,
+                required: player_color,
+                actual: hex_color,
+            }),
+            map::Terrain::Water => Err(AoiError::WaterHex { pos }),
+        }
     }
 
     pub fn player_to_move(&self) -> Result<PlayerId> {
-        let num_players = self.factions.len();
+        let num_players = self.state.factions.len();
         if self.placed.len() == num_players * 2 {
-            Err(create_error("All buildings have already been placed"))
+            Err(AoiError::AllBuildingsPlaced)
         } else if self.placed.len() < num_players {
             Ok(self.placed.len())
         } else {
@@ -80,12 +98,10 @@ impl BuildingPlacer {
     }
 
     pub fn finish(self) -> Result<IncomePhase> {
-        if self.placed.len() == 2 * self.factions.len() {
-            Ok(IncomePhase {})
+        if self.placed.len() == 2 * self.state.factions.len() {
+            Ok(IncomePhase::new(self.state))
         } else {
-            Err(create_error(
-                "All players have not placed all their buildings",
-            ))
+            Err(AoiError::PlacementNotFinished)
         }
     }
 }
@@ -179,7 +195,8 @@ mod tests {
         assert!(placer.player_to_move().is_err());
     }
 
-    #[parameterized(color = { Color::Yellow, Color::Brown, Color::Black, Color::Blue, Color::Green, Color::Gray, Color::Red })]
+    // Yellow may also dig, see yellow_digs_with_setup_spade
+    #[parameterized(color = { Color::Brown, Color::Black, Color::Blue, Color::Green, Color::Gray, Color::Red })]
     fn only_allowed_to_place_on_native_color(color: Color) {
         // Let player ID = 0 have the color to test
         let player_id: PlayerId = 0;
@@ -233,6 +250,25 @@ mod tests {
         assert!(placer.place(player_id, outside_pos_2).is_err());
     }
 
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn placed_building_is_put_on_map(num_players: usize) {
+        let mut placer = create_test_building_placer(num_players);
+        let pos = (1, 0);
+
+        placer.place(0, pos).unwrap();
+
+        assert_eq!(
+            placer.state.map[pos.0][pos.1].building,
+            Some((0, Building::Workshop))
+        );
+        assert_eq!(
+            placer.state.factions[0]
+                .supply()
+                .remaining(Building::Workshop),
+            8
+        );
+    }
+
     #[parameterized(num_players = { 2, 3, 4, 5 })]
     fn cant_place_building_on_already_occupied_hex(num_players: usize) {
         let mut placer = create_test_building_placer(num_players);
@@ -242,4 +278,37 @@ mod tests {
 
         assert!(placer.place(1, pos).is_err());
     }
+
+    #[test]
+    fn yellow_digs_with_setup_spade() {
+        let factions = vec![
+            Faction::new(&Race::Raceless, &Color::Yellow),
+            Faction::new(&Race::Raceless, &Color::Colorless),
+        ];
+        let mut placer = BuildingPlacer::new(factions, map::open_map(map::MapId::Debug));
+
+        // Black is two spades from yellow, brown and red only one
+        assert!(!placer.valid_placement(0, (3, 0)));
+        assert!(placer.valid_placement(0, (8, 0)));
+        placer.place(0, (2, 0)).unwrap();
+
+        assert_eq!(
+            placer.state.map[2][0].terrain,
+            map::Terrain::Land(Color::Yellow)
+        );
+        assert_eq!(placer.state.factions[0].setup_spades(), 0);
+
+        // The spade is used up
+        placer.place(1, (5, 0)).unwrap();
+        placer.place(1, (5, 1)).unwrap();
+        assert_eq!(
+            placer.place(0, (2, 1)),
+            Err(AoiError::WrongColor {
+                pos: (2, 1),
+                required: Color::Yellow,
+                actual: Color::Brown
+            })
+        );
+        placer.place(0, (1, 0)).unwrap();
+    }
 }
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+
+use crate::error::AoiError;
+use crate::gamephase::finalscoring::FinalScoring;
+use crate::gamephase::incomephase::IncomePhase;
+use crate::gamephase::{GameState, PlayerId};
+use crate::map;
+use crate::reachability;
+use crate::resources::{Books, Coins, Power, Resources, Scholars, Tools};
+use crate::scoringtile::RightSideReward;
+use crate::{terraform, Result};
+
+/// The end of a round. Players are rewarded by the right side of the round's scoring
+/// tile, after which those rewarded with spades dig them in the next turn order.
+#[derive(Clone)]
+pub struct CleanupPhase {
+    state: GameState,
+    spades: VecDeque<(PlayerId, u32)>, // Spades left to dig, in turn order
+}
+
+pub enum NextPhase {
+    Income(IncomePhase),
+    FinalScoring(FinalScoring),
+}
+
+impl CleanupPhase {
+    /// Pays the rewards of the round. The order in which players passed becomes the turn
+    /// order of the next round. No rewards are paid after the last round.
+    pub fn new(mut state: GameState, pass_order: Vec<PlayerId>) -> CleanupPhase {
+        state.turn_order = pass_order;
+
+        let mut spades = VecDeque::new();
+        if let Some(tile) = state.scoring_tile().filter(|_| !state.is_last_round()) {
+            let right = tile.right_side;
+            for &player_id in &state.turn_order {
+                let faction = &mut state.factions[player_id];
+                let amount =
+                    right.rew_amount * (faction.disc_level(right.disc) / right.disc_requirement);
+                if amount == 0 {
+                    continue;
+                }
+
+                match right.rew {
+                    RightSideReward::Books => {
+                        faction.gain(&Resources::from((right.disc, Books(amount))))
+                    }
+                    RightSideReward::Coins => faction.gain(&Resources::from(Coins(amount))),
+                    RightSideReward::Tools => faction.gain(&Resources::from(Tools(amount))),
+                    RightSideReward::Power => faction.gain(&Resources::from(Power(amount))),
+                    RightSideReward::Scholars => faction.gain(&Resources::from(Scholars(amount))),
+                    RightSideReward::Spades => spades.push_back((player_id, amount)),
+                }
+            }
+        }
+
+        CleanupPhase { state, spades }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The player to dig next, while any spades are left.
+    pub fn player_to_move(&self) -> Result<PlayerId> {
+        self.spades
+            .front()
+            .map(|&(player_id, _)| player_id)
+            .ok_or(AoiError::NoSpadesPending)
+    }
+
+    /// Spades the player to move has left to dig.
+    pub fn spades_left(&self) -> u32 {
+        self.spades.front().map_or(0, |&(_, spades)| spades)
+    }
+
+    /// Digs a reachable hex toward the player's home color, using as many of the spades
+    /// as needed. The player's turn is over once all spades are used.
+    pub fn dig(&mut self, player_id: PlayerId, pos: map::Pos) -> Result<()> {
+        self.ensure_turn(player_id)?;
+        if !map::inside_bounds(pos) {
+            return Err(AoiError::OutsideMap { pos });
+        }
+
+        let map = &mut self.state.map;
+        let faction = &mut self.state.factions[player_id];
+        let own_buildings = map::buildings_of(map, player_id);
+        if !reachability::reachable_by(map, &own_buildings, faction).contains(pos) {
+            return Err(AoiError::NotReachable { pos });
+        }
+
+        let spades = &mut self.spades.front_mut().unwrap().1;
+        *spades -= terraform::dig(map, pos, faction, *spades)?;
+        if *spades == 0 {
+            self.spades.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// Gives up the spades the player has left.
+    pub fn decline_spades(&mut self, player_id: PlayerId) -> Result<()> {
+        self.ensure_turn(player_id)?;
+        self.spades.pop_front();
+
+        Ok(())
+    }
+
+    /// Ends the round once all spades are dug, and after the last round the game proceeds
+    /// to final scoring.
+    pub fn finish(mut self) -> Result<NextPhase> {
+        if let Ok(player_id) = self.player_to_move() {
+            return Err(AoiError::SpadesPending { player_id });
+        }
+
+        if self.state.is_last_round() {
+            Ok(NextPhase::FinalScoring(FinalScoring::new(self.state)))
+        } else {
+            self.state.round += 1;
+            self.state.book_actions_taken.clear();
+            Ok(NextPhase::Income(IncomePhase::new(self.state)))
+        }
+    }
+
+    fn ensure_turn(&self, player_id: PlayerId) -> Result<()> {
+        if self.player_to_move()? == player_id {
+            Ok(())
+        } else {
+            Err(AoiError::NotPlayersTurn { player_id })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::building::Building;
+    use crate::common::{Color, Discipline};
+    use crate::faction::Faction;
+    use crate::gamephase::actionphase::ActionPhase;
+    use crate::gamephase::buildingplacement::BuildingPlacer;
+    use crate::gamephase::NUM_ROUNDS;
+    use crate::map::{self, Terrain};
+    use crate::race::Race;
+    use crate::scoringtile::{self, ScoringTile};
+
+    use parameterized::parameterized;
+
+    fn pass_all(mut phase: ActionPhase) -> CleanupPhase {
+        while let Ok(player_id) = phase.player_to_move() {
+            phase.pass(player_id, None).unwrap();
+        }
+
+        phase.finish().unwrap()
+    }
+
+    #[test]
+    fn pass_order_becomes_next_turn_order() {
+        let factions = (0..3)
+            .map(|_| Faction::new(&Race::Raceless, &Color::Colorless))
+            .collect();
+        let state = GameState::new(factions, map::open_map(map::MapId::Debug));
+        let cleanup = CleanupPhase::new(state, vec![1, 2, 0]);
+
+        match cleanup.finish().unwrap() {
+            NextPhase::Income(income) => {
+                assert_eq!(income.state().turn_order(), &vec![1, 2, 0]);
+                assert_eq!(income.state().round(), 1);
+            }
+            NextPhase::FinalScoring(_) => panic!("Game ended after the first round"),
+        }
+    }
+
+    fn create_state(colors: &[Color], rew: RightSideReward, disc: Discipline) -> GameState {
+        let factions = colors
+            .iter()
+            .map(|color| Faction::new(&Race::Raceless, color))
+            .collect();
+        let mut state = GameState::new(factions, map::open_map(map::MapId::Debug));
+        let tile: ScoringTile = scoringtile::all_scoring_tiles()
+            .into_iter()
+            .find(|tile| tile.right_side.rew == rew && tile.right_side.disc == disc)
+            .unwrap();
+        state.scoring_tiles = vec![tile];
+
+        state
+    }
+
+    #[test]
+    fn rewards_scale_with_discipline_level() {
+        // One tool for every second step on the banking track
+        let mut state = create_state(
+            &[Color::Colorless, Color::Colorless, Color::Colorless],
+            RightSideReward::Tools,
+            Discipline::Banking,
+        );
+        for (player_id, level) in [5, 2, 1].into_iter().enumerate() {
+            state.factions[player_id].incr_disc(Discipline::Banking, level);
+        }
+
+        let cleanup = CleanupPhase::new(state, vec![0, 1, 2]);
+
+        let tools = cleanup
+            .state()
+            .factions()
+            .iter()
+            .map(|f| f.tools())
+            .collect::<Vec<_>>();
+        assert_eq!(tools, [Tools(5), Tools(4), Tools(3)]);
+        assert!(cleanup.player_to_move().is_err());
+    }
+
+    #[test]
+    fn no_rewards_after_last_round() {
+        let mut state = create_state(
+            &[Color::Colorless, Color::Colorless],
+            RightSideReward::Tools,
+            Discipline::Banking,
+        );
+        state.factions[0].incr_disc(Discipline::Banking, 4);
+        state.round = NUM_ROUNDS - 1;
+        state.scoring_tiles = vec![state.scoring_tiles[0]; NUM_ROUNDS];
+
+        let cleanup = CleanupPhase::new(state, vec![0, 1]);
+
+        assert_eq!(cleanup.state().factions()[0].tools(), Tools(3));
+    }
+
+    fn create_spade_cleanup() -> CleanupPhase {
+        let mut state = create_state(
+            &[Color::Brown, Color::Yellow],
+            RightSideReward::Spades,
+            Discipline::Medicine,
+        );
+        state.map[1][1].building = Some((1, Building::Workshop));
+        state.factions[1].incr_disc(Discipline::Medicine, 8); // Two spades
+
+        CleanupPhase::new(state, vec![0, 1])
+    }
+
+    #[test]
+    fn spades_are_dug_toward_home_color() {
+        let mut cleanup = create_spade_cleanup();
+        assert_eq!(cleanup.player_to_move(), Ok(1));
+        assert_eq!(cleanup.spades_left(), 2);
+
+        // Brown is one spade from yellow
+        cleanup.dig(1, (2, 2)).unwrap();
+        assert_eq!(
+            cleanup.state().map()[2][2].terrain,
+            Terrain::Land(Color::Yellow)
+        );
+        assert_eq!(cleanup.spades_left(), 1);
+
+        assert_eq!(
+            cleanup.dig(1, (2, 2)),
+            Err(AoiError::AlreadyColor {
+                pos: (2, 2),
+                color: Color::Yellow
+            })
+        );
+        assert_eq!(
+            cleanup.dig(1, (1, 8)),
+            Err(AoiError::NotReachable { pos: (1, 8) })
+        );
+        assert_eq!(
+            cleanup.dig(0, (2, 2)),
+            Err(AoiError::NotPlayersTurn { player_id: 0 })
+        );
+    }
+
+    #[test]
+    fn round_waits_for_spades() {
+        let mut cleanup = create_spade_cleanup();
+        assert!(matches!(
+            cleanup.clone().finish(),
+            Err(AoiError::SpadesPending { player_id: 1 })
+        ));
+
+        cleanup.decline_spades(1).unwrap();
+
+        assert_eq!(cleanup.player_to_move(), Err(AoiError::NoSpadesPending));
+        assert!(cleanup.finish().is_ok());
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn game_ends_in_final_scoring_after_six_rounds(num_players: usize) {
+        let factions = (0..num_players)
+            .map(|_| Faction::new(&Race::Raceless, &Color::Colorless))
+            .collect();
+        let mut placer = BuildingPlacer::new(factions, map::open_map(map::MapId::Debug));
+        for (i, player_id) in (0..num_players).chain((0..num_players).rev()).enumerate() {
+            placer.place(player_id, (1 + i / 6, i % 6)).unwrap();
+        }
+
+        let mut income = placer.finish().unwrap();
+        let mut rounds_played = 0;
+        let final_scoring = loop {
+            rounds_played += 1;
+            match pass_all(income.finish()).finish().unwrap() {
+                NextPhase::Income(next) => income = next,
+                NextPhase::FinalScoring(final_scoring) => break final_scoring,
+            }
+        };
+
+        assert_eq!(rounds_played, NUM_ROUNDS);
+        assert_eq!(final_scoring.state().round(), NUM_ROUNDS - 1);
+    }
+}
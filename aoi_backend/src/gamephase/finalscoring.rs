@@ -0,0 +1,241 @@
+use enum_iterator::all;
+use serde::Serialize;
+
+use crate::common::{Discipline, VP};
+use crate::gamephase::{GameState, PlayerId};
+use crate::map;
+use crate::resources::{Coins, Power, Scholars, Tools};
+
+const DISCIPLINE_VP: [u32; 3] = [8, 4, 2];
+const AREA_VP: [u32; 3] = [18, 12, 6];
+const COINS_PER_VP: u32 = 3;
+
+/// The VP each player scored at the end of the game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FinalScore {
+    pub player_id: PlayerId,
+    pub disciplines: [VP; 4], // Indexed by Discipline
+    pub area_size: usize,
+    pub area: VP,
+    pub resources: VP,
+    pub total: VP,
+}
+
+/// The game is over once this phase has been reached.
+#[derive(Clone)]
+pub struct FinalScoring {
+    state: GameState,
+    scores: Vec<FinalScore>, // Indices map to player IDs
+}
+
+impl FinalScoring {
+    /// Scores the game and awards the VP to the players.
+    pub fn new(mut state: GameState) -> FinalScoring {
+        let scores = final_scores(&state);
+        for score in &scores {
+            state.factions[score.player_id].gain_vp(score.total);
+        }
+
+        FinalScoring { state, scores }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn scores(&self) -> &Vec<FinalScore> {
+        &self.scores
+    }
+}
+
+fn final_scores(state: &GameState) -> Vec<FinalScore> {
+    let num_players = state.factions.len();
+
+    let mut disciplines = vec![[VP(0); 4]; num_players];
+    for disc in all::<Discipline>() {
+        let levels = state
+            .factions
+            .iter()
+            .map(|f| f.disc_level(disc) as usize)
+            .collect::<Vec<_>>();
+        for (player_id, vp) in majority_vp(&levels, &DISCIPLINE_VP).into_iter().enumerate() {
+            disciplines[player_id][disc as usize] = vp;
+        }
+    }
+
+    let area_sizes = (0..num_players)
+        .map(|player_id| map::largest_connected_area(&state.map, &state.bridges, player_id))
+        .collect::<Vec<_>>();
+    let area = majority_vp(&area_sizes, &AREA_VP);
+
+    (0..num_players)
+        .map(|player_id| {
+            let resources = leftover_vp(state, player_id);
+            let total = disciplines[player_id]
+                .iter()
+                .chain([&area[player_id], &resources])
+                .fold(VP(0), |acc, vp| VP(acc.0 + vp.0));
+
+            FinalScore {
+                player_id,
+                disciplines: disciplines[player_id],
+                area_size: area_sizes[player_id],
+                area: area[player_id],
+                resources,
+                total,
+            }
+        })
+        .collect()
+}
+
+/// Hands out `rewards` from the highest value down. Tied players add up the rewards of the
+/// places they take and split them evenly, rounded down. Players with nothing get nothing.
+fn majority_vp(values: &[usize], rewards: &[u32]) -> Vec<VP> {
+    let mut vp = vec![VP(0); values.len()];
+
+    let mut place = 0;
+    let mut ranked = values
+        .iter()
+        .copied()
+        .filter(|&value| value > 0)
+        .collect::<Vec<_>>();
+    ranked.sort_unstable_by(|a, b| b.cmp(a));
+    ranked.dedup();
+    for value in ranked {
+        let tied = (0..values.len())
+            .filter(|&player_id| values[player_id] == value)
+            .collect::<Vec<_>>();
+        let pot = rewards.iter().skip(place).take(tied.len()).sum::<u32>();
+        for &player_id in &tied {
+            vp[player_id] = VP(pot / tied.len() as u32);
+        }
+        place += tied.len();
+    }
+
+    vp
+}
+
+// Every tool, coin, scholar, book and power in bowl 3 counts as a coin, although some
+// races get more for their scholars
+fn leftover_vp(state: &GameState, player_id: PlayerId) -> VP {
+    let faction = &state.factions[player_id];
+    let resources = faction.resources();
+    let scholars = resources.get::<Scholars>().0;
+    let coins = resources.get::<Tools>().0
+        + resources.get::<Coins>().0
+        + resources.total_books().0
+        + resources.get::<Power>().0;
+
+    match faction.race().vp_per_leftover_scholar() {
+        VP(0) => VP((coins + scholars) / COINS_PER_VP),
+        per_scholar => VP(coins / COINS_PER_VP + scholars * per_scholar.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::building::Building;
+    use crate::common::Color;
+    use crate::faction::Faction;
+    use crate::map::Bridge;
+    use crate::race::Race;
+    use crate::resources::Resources;
+
+    use parameterized::parameterized;
+
+    fn create_state(num_players: usize) -> GameState {
+        let factions = (0..num_players)
+            .map(|_| Faction::new(&Race::Raceless, &Color::Colorless))
+            .collect();
+
+        GameState::new(factions, map::open_map(map::MapId::Debug))
+    }
+
+    fn build(state: &mut GameState, player_id: PlayerId, positions: &[map::Pos]) {
+        for &(row, col) in positions {
+            state.map[row][col].building = Some((player_id, Building::Workshop));
+        }
+    }
+
+    #[test]
+    fn majority_is_split_between_tied_players() {
+        assert_eq!(
+            majority_vp(&[3, 2, 1], &DISCIPLINE_VP),
+            [VP(8), VP(4), VP(2)]
+        );
+        assert_eq!(
+            majority_vp(&[3, 3, 1], &DISCIPLINE_VP),
+            [VP(6), VP(6), VP(2)]
+        );
+        assert_eq!(
+            majority_vp(&[5, 2, 2, 2], &DISCIPLINE_VP),
+            [VP(8), VP(2), VP(2), VP(2)]
+        );
+        assert_eq!(majority_vp(&[4, 4, 4, 4], &AREA_VP), [VP(9); 4]);
+        assert_eq!(
+            majority_vp(&[1, 1, 0], &DISCIPLINE_VP),
+            [VP(6), VP(6), VP(0)]
+        );
+        assert_eq!(majority_vp(&[0, 0], &DISCIPLINE_VP), [VP(0), VP(0)]);
+    }
+
+    #[test]
+    fn bridges_join_areas() {
+        let mut state = create_state(2);
+        build(&mut state, 0, &[(1, 1), (1, 2), (5, 5), (5, 6)]);
+        build(&mut state, 1, &[(1, 8), (2, 8), (3, 8)]);
+
+        assert_eq!(final_scores(&state)[0].area, VP(12));
+        assert_eq!(final_scores(&state)[1].area, VP(18));
+
+        state.bridges.push(Bridge {
+            player_id: 0,
+            ends: ((1, 2), (5, 5)),
+        });
+        let scores = final_scores(&state);
+
+        assert_eq!((scores[0].area_size, scores[0].area), (4, VP(18)));
+        assert_eq!((scores[1].area_size, scores[1].area), (3, VP(12)));
+    }
+
+    #[test]
+    fn leftover_resources_are_converted() {
+        let mut state = create_state(2);
+        state.factions[1].gain(&(Resources::from(Tools(2)) + &Resources::from(Scholars(1))));
+        state.factions[1].gain_power(12); // Seven reach bowl 3
+
+        // 15 coins and 3 tools
+        assert_eq!(final_scores(&state)[0].resources, VP(6));
+        // 15 coins, 5 tools, a scholar and 7 power
+        assert_eq!(final_scores(&state)[1].resources, VP(9));
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn scores_are_added_to_vp(num_players: usize) {
+        let mut state = create_state(num_players);
+        build(&mut state, 0, &[(1, 1), (1, 2)]);
+        state.factions[0].incr_disc(Discipline::Law, 3);
+        state.factions[num_players - 1].incr_disc(Discipline::Law, 1);
+        let vp_before = state.factions.iter().map(|f| f.vp()).collect::<Vec<_>>();
+
+        let final_scoring = FinalScoring::new(state);
+        let scores = final_scoring.scores();
+
+        assert_eq!(scores.len(), num_players);
+        assert_eq!(scores[0].disciplines[Discipline::Law as usize], VP(8));
+        assert_eq!(
+            scores[num_players - 1].disciplines[Discipline::Law as usize],
+            VP(4)
+        );
+        assert_eq!(scores[0].area, VP(18));
+        for (player_id, score) in scores.iter().enumerate() {
+            assert_eq!(score.player_id, player_id);
+            assert_eq!(
+                final_scoring.state().factions()[player_id].vp(),
+                VP(vp_before[player_id].0 + score.total.0)
+            );
+        }
+    }
+}
@@ -1 +1,55 @@
-pub struct IncomePhase {}
+use crate::gamephase::actionphase::ActionPhase;
+use crate::gamephase::GameState;
+
+pub struct IncomePhase {
+    state: GameState,
+}
+
+impl IncomePhase {
+    pub fn new(state: GameState) -> IncomePhase {
+        IncomePhase { state }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn finish(mut self) -> ActionPhase {
+        for faction in self.state.factions.iter_mut() {
+            faction.collect_income();
+        }
+
+        ActionPhase::new(self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bonustile::BonusTile;
+    use crate::common::Color;
+    use crate::faction::Faction;
+    use crate::map;
+    use crate::race::Race;
+    use crate::resources::Resources;
+
+    #[test]
+    fn all_players_collect_income() {
+        let mut factions = vec![
+            Faction::new(&Race::Raceless, &Color::Colorless),
+            Faction::new(&Race::Raceless, &Color::Colorless),
+        ];
+        factions[0].set_bonus_tile(Some(BonusTile::BonCoins));
+        factions[1].set_bonus_tile(Some(BonusTile::BonScholar));
+        let state = GameState::new(factions, map::open_map(map::MapId::Debug));
+
+        let action_phase = IncomePhase::new(state).finish();
+
+        let json = |f: &Faction| serde_json::to_value(f).unwrap();
+        let factions = action_phase.state().factions();
+        assert_eq!(json(&factions[0])["coins"], 21);
+        assert_eq!(json(&factions[1])["scholars"], 1);
+        assert_ne!(factions[0].total_income(), Resources::none());
+    }
+}
@@ -1,9 +1,15 @@
-use crate::{error::create_error, Result};
+use crate::{error::AoiError, Result};
 
 use std::iter::zip;
 
-use itertools::Itertools;
-use serde_json::{json, Map};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+fn json_diff_error(reason: &str) -> AoiError {
+    AoiError::JsonDiff {
+        reason: String::from(reason),
+    }
+}
 
 pub fn contains_duplicates<T: Eq>(vec: &Vec<T>) -> bool {
     for (i, a) in zip(0.., vec) {
@@ -17,115 +23,171 @@ pub fn contains_duplicates<T: Eq>(vec: &Vec<T>) -> bool {
     false
 }
 
-fn json_obj_diff_helper(
-    old: &serde_json::Value,
-    new: &serde_json::Value,
-) -> Result<serde_json::Value> {
-    use serde_json::Value;
+/// One operation of a JSON Patch (RFC 6902). Paths are JSON Pointers (RFC 6901).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+fn json_obj_diff_helper(path: &str, old: &Value, new: &Value, ops: &mut Vec<PatchOp>) {
+    if old == new {
+        return;
+    }
 
     match (old, new) {
-        (Value::Bool(a), Value::Bool(b)) => {
-            if a != b {
-                Ok(Value::Bool(*b))
-            } else {
-                Ok(Value::Null)
+        (Value::Object(a), Value::Object(b)) => {
+            for k in a.keys().filter(|k| !b.contains_key(*k)) {
+                ops.push(PatchOp::Remove {
+                    path: format!("{}/{}", path, escape_token(k)),
+                });
             }
-        }
-        (Value::Number(a), Value::Number(b)) => {
-            if a != b {
-                Ok(Value::Number(b.clone()))
-            } else {
-                Ok(Value::Null)
+            for (k, v_new) in b {
+                let member_path = format!("{}/{}", path, escape_token(k));
+                match a.get(k) {
+                    Some(v_old) => json_obj_diff_helper(&member_path, v_old, v_new, ops),
+                    None => ops.push(PatchOp::Add {
+                        path: member_path,
+                        value: v_new.clone(),
+                    }),
+                }
             }
         }
-        (Value::String(a), Value::String(b)) => {
-            if a != b {
-                Ok(Value::String(b.clone()))
-            } else {
-                Ok(Value::Null)
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (e1, e2)) in zip(a, b).enumerate() {
+                json_obj_diff_helper(&format!("{}/{}", path, i), e1, e2, ops);
+            }
+            for (i, e) in b.iter().enumerate().skip(a.len()) {
+                ops.push(PatchOp::Add {
+                    path: format!("{}/{}", path, i),
+                    value: e.clone(),
+                });
+            }
+            // Removed from the back so that the indices stay valid
+            for i in (b.len()..a.len()).rev() {
+                ops.push(PatchOp::Remove {
+                    path: format!("{}/{}", path, i),
+                });
             }
         }
-        (Value::Array(a), Value::Array(b)) => {
-            if a != b {
-                if a.len() != b.len() {
-                    return Err(create_error(
-                        "Json arrays must have equal number of elements",
-                    ));
-                }
+        _ => ops.push(PatchOp::Replace {
+            path: String::from(path),
+            value: new.clone(),
+        }),
+    }
+}
 
-                let mut res = Vec::new();
-                for (e1, e2) in zip(a, b) {
-                    match json_obj_diff_helper(&e1, &e2) {
-                        Ok(v) => res.push(v),
-                        Err(e) => return Err(e),
-                    }
-                }
+/// Computes the operations that turn the JSON object `old` into `new`. Applying them
+/// with `apply_diff` gives back `new`.
+pub fn json_obj_diff(old: &Value, new: &Value) -> Result<Vec<PatchOp>> {
+    match (old, new) {
+        (Value::Object(_), Value::Object(_)) => (),
+        _ => return Err(json_diff_error("Json values are not object types")),
+    };
 
-                if res.iter().all(|v| *v == Value::Null) {
-                    // Arrays are identical
-                    return Ok(Value::Null);
-                }
+    let mut ops = Vec::new();
+    json_obj_diff_helper("", old, new, &mut ops);
 
-                let res = zip(0.., res)
-                    .map(|(i, v)| if v == Value::Null { b[i].clone() } else { v })
-                    .collect_vec();
+    Ok(ops)
+}
 
-                Ok(Value::Array(res))
-            } else {
-                Ok(Value::Null)
-            }
-        }
-        (Value::Object(a), Value::Object(b)) => {
-            if a != b {
-                if a.len() != b.len() {
-                    return Err(create_error(
-                        "Json objects must have equal number of members",
-                    ));
-                }
+// Splits a JSON Pointer into the pointer of the parent and the last (unescaped) token.
+fn split_path(path: &str) -> Result<(&str, String)> {
+    match path.rfind('/') {
+        Some(idx) => Ok((&path[..idx], unescape_token(&path[idx + 1..]))),
+        None => Err(json_diff_error("Path must point into an object or array")),
+    }
+}
 
-                let mut res = Map::new();
-                for (k, v_new) in b {
-                    if !a.contains_key(k) {
-                        return Err(create_error("Json object must contains same keys"));
-                    }
-                    let v_old = a.get(k).unwrap();
-
-                    match json_obj_diff_helper(&v_old, &v_new) {
-                        Ok(Value::Null) => (),
-                        Ok(v) => {
-                            res.insert(k.clone(), v);
-                        }
-                        Err(e) => return Err(e),
-                    }
-                }
+// Index of an array element, or the length of the array to append at the end.
+fn array_index(token: &str, len: usize) -> Result<usize> {
+    match token.parse::<usize>() {
+        Ok(idx) if idx <= len => Ok(idx),
+        _ if token == "-" => Ok(len),
+        _ => Err(json_diff_error("Invalid array index")),
+    }
+}
 
-                Ok(Value::Object(res))
-            } else {
-                Ok(json!({}))
+fn apply_op(doc: &mut Value, op: &PatchOp) -> Result<()> {
+    let path = match op {
+        PatchOp::Add { path, .. } | PatchOp::Remove { path } | PatchOp::Replace { path, .. } => {
+            path
+        }
+    };
+    let (parent_path, token) = split_path(path)?;
+    let parent = doc
+        .pointer_mut(parent_path)
+        .ok_or_else(|| json_diff_error("Path does not exist"))?;
+
+    match (parent, op) {
+        (Value::Object(obj), PatchOp::Add { value, .. }) => {
+            obj.insert(token, value.clone());
+        }
+        (Value::Object(obj), PatchOp::Remove { .. }) => {
+            obj.remove(&token)
+                .ok_or_else(|| json_diff_error("Path does not exist"))?;
+        }
+        (Value::Object(obj), PatchOp::Replace { value, .. }) => {
+            let member = obj
+                .get_mut(&token)
+                .ok_or_else(|| json_diff_error("Path does not exist"))?;
+            *member = value.clone();
+        }
+        (Value::Array(arr), PatchOp::Add { value, .. }) => {
+            let idx = array_index(&token, arr.len())?;
+            arr.insert(idx, value.clone());
+        }
+        (Value::Array(arr), PatchOp::Remove { .. }) => {
+            let idx = array_index(&token, arr.len())?;
+            if idx == arr.len() {
+                return Err(json_diff_error("Path does not exist"));
             }
+            arr.remove(idx);
+        }
+        (Value::Array(arr), PatchOp::Replace { value, .. }) => {
+            let idx = array_index(&token, arr.len())?;
+            let member = arr
+                .get_mut(idx)
+                .ok_or_else(|| json_diff_error("Path does not exist"))?;
+            *member = value.clone();
         }
-        _ => Err(create_error("Mismatched types")),
+        _ => return Err(json_diff_error("Path must point into an object or array")),
     }
-}
 
-pub fn json_obj_diff(
-    old: &serde_json::Value,
-    new: &serde_json::Value,
-) -> Result<serde_json::Value> {
-    use serde_json::Value;
+    Ok(())
+}
 
-    match (old, new) {
-        (Value::Object(_), Value::Object(_)) => (),
-        _ => return Err(create_error("Json values are not object types")),
-    };
+/// Applies the operations of a diff from `json_obj_diff` in order. Nothing is changed
+/// unless all of them can be applied.
+pub fn apply_diff(doc: &Value, diff: &[PatchOp]) -> Result<Value> {
+    let mut res = doc.clone();
+    for op in diff {
+        apply_op(&mut res, op)?;
+    }
 
-    json_obj_diff_helper(old, new)
+    Ok(res)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use assert_json_diff::assert_json_include;
+    use serde_json::json;
+
+    fn assert_round_trip(old: &Value, new: &Value) {
+        let diff = json_obj_diff(old, new).unwrap();
+
+        assert_eq!(apply_diff(old, &diff).unwrap(), *new);
+    }
 
     #[test]
     fn contains_duplicates_positive() {
@@ -154,13 +216,13 @@ mod tests {
 
         for json1 in &jsons {
             for json2 in &jsons {
-                assert!(json_obj_diff(&json1, &json2).is_err());
+                assert!(json_obj_diff(json1, json2).is_err());
             }
         }
     }
 
     #[test]
-    fn json_obj_diff_equal_objs_returns_empty_obj() {
+    fn json_obj_diff_equal_objs_returns_no_ops() {
         let obj = json!(
             {
                 "foo": "old",
@@ -170,11 +232,11 @@ mod tests {
 
         let diff = json_obj_diff(&obj, &obj.clone()).unwrap();
 
-        assert_json_include!(actual: diff, expected: json!({}));
+        assert!(diff.is_empty());
     }
 
     #[test]
-    fn json_obj_diff_one_changed_value_is_returned() {
+    fn json_obj_diff_one_changed_value_is_replaced() {
         let obj1 = json!(
             {
                 "foo": "old",
@@ -190,32 +252,40 @@ mod tests {
 
         let diff = json_obj_diff(&obj1, &obj2).unwrap();
 
-        let expected = json!({
-            "foo": "new"
-        });
-        assert_json_include!(actual: diff, expected: expected);
+        assert_eq!(
+            diff,
+            vec![PatchOp::Replace {
+                path: String::from("/foo"),
+                value: json!("new")
+            }]
+        );
     }
 
     #[test]
-    fn json_obj_diff_changed_value_type_returns_error() {
+    fn json_obj_diff_changed_value_type_is_replaced() {
         let obj1 = json!(
             {
                 "foo": "bar",
-                "key": 2
+                "key": 2,
+                "building": null
             }
         );
         let obj2 = json!(
             {
                 "foo": "bar",
-                "key": true
+                "key": true,
+                "building": [0, "Workshop"]
             }
         );
 
-        assert!(json_obj_diff(&obj1, &obj2).is_err());
+        let diff = json_obj_diff(&obj1, &obj2).unwrap();
+
+        assert_eq!(diff.len(), 2);
+        assert_round_trip(&obj1, &obj2);
     }
 
     #[test]
-    fn json_obj_changed_vec_length_returns_error() {
+    fn json_obj_diff_changed_vec_length() {
         let obj1 = json!(
             {
                 "foo": "bar",
@@ -229,12 +299,32 @@ mod tests {
             }
         );
 
-        assert!(json_obj_diff(&obj1, &obj2).is_err());
-        assert!(json_obj_diff(&obj2, &obj1).is_err());
+        assert_eq!(
+            json_obj_diff(&obj1, &obj2).unwrap(),
+            vec![PatchOp::Remove {
+                path: String::from("/vec/2")
+            }]
+        );
+        assert_eq!(
+            json_obj_diff(&obj2, &obj1).unwrap(),
+            vec![PatchOp::Add {
+                path: String::from("/vec/2"),
+                value: json!("baz")
+            }]
+        );
+    }
+
+    #[test]
+    fn json_obj_diff_shrunk_vec_round_trips() {
+        let obj1 = json!({ "vec": [1, 2, 3, 4, 5] });
+        let obj2 = json!({ "vec": [1, 7] });
+
+        assert_round_trip(&obj1, &obj2);
+        assert_round_trip(&obj2, &obj1);
     }
 
     #[test]
-    fn json_obj_diff_changed_key_returns_error() {
+    fn json_obj_diff_changed_key() {
         let obj1 = json!(
             {
                 "foo": "bar"
@@ -246,7 +336,20 @@ mod tests {
             }
         );
 
-        assert!(json_obj_diff(&obj1, &obj2).is_err());
+        let diff = json_obj_diff(&obj1, &obj2).unwrap();
+
+        assert_eq!(
+            diff,
+            vec![
+                PatchOp::Remove {
+                    path: String::from("/foo")
+                },
+                PatchOp::Add {
+                    path: String::from("/baz"),
+                    value: json!("bar")
+                }
+            ]
+        );
     }
 
     #[test]
@@ -268,18 +371,19 @@ mod tests {
 
         let diff = json_obj_diff(&obj1, &obj2).unwrap();
 
-        let expected = json!(
-            {
-                "vec": [1, 2, "changed"],
-                "key": 5678
-            }
-        );
-
-        assert_json_include!(actual: diff, expected: expected);
+        assert_eq!(diff.len(), 2);
+        assert!(diff.contains(&PatchOp::Replace {
+            path: String::from("/vec/2"),
+            value: json!("changed")
+        }));
+        assert!(diff.contains(&PatchOp::Replace {
+            path: String::from("/key"),
+            value: json!(5678)
+        }));
     }
 
     #[test]
-    fn json_obj_diff_added_or_removed_key_returns_error() {
+    fn json_obj_diff_added_or_removed_key() {
         let obj1 = json!(
             {
                 "foo": "bar",
@@ -292,8 +396,8 @@ mod tests {
             }
         );
 
-        assert!(json_obj_diff(&obj1, &obj2).is_err());
-        assert!(json_obj_diff(&obj2, &obj1).is_err());
+        assert_round_trip(&obj1, &obj2);
+        assert_round_trip(&obj2, &obj1);
     }
 
     #[test]
@@ -315,16 +419,17 @@ mod tests {
 
         let diff = json_obj_diff(&obj1, &obj2).unwrap();
 
-        let expected = json!({
-            "nested": {
-                "nested_foo": "new"
-            }
-        });
-        assert_json_include!(actual: diff, expected: expected);
+        assert_eq!(
+            diff,
+            vec![PatchOp::Replace {
+                path: String::from("/nested/nested_foo"),
+                value: json!("new")
+            }]
+        );
     }
 
     #[test]
-    fn json_obj_diff_nested_objs_do_not_match() {
+    fn json_obj_diff_nested_obj_changes_type() {
         let obj1 = json!({
             "nested": {
                 "nested_foo": "bar",
@@ -334,11 +439,11 @@ mod tests {
         let obj2 = json!({
             "nested": {
                 "nested_foo": "bar",
-                "nested_baz": "changed type", // Illegal
+                "nested_baz": "changed type",
             }
         });
 
-        assert!(json_obj_diff(&obj1, &obj2).is_err());
+        assert_round_trip(&obj1, &obj2);
     }
 
     #[test]
@@ -359,12 +464,13 @@ mod tests {
 
         let diff = json_obj_diff(&obj1, &obj2).unwrap();
 
-        let expected = json!(
-            {
-                "changed_vec": [1, 2, { "nested_foo": 6 }]
-            }
+        assert_eq!(
+            diff,
+            vec![PatchOp::Replace {
+                path: String::from("/changed_vec/2/nested_foo"),
+                value: json!(6)
+            }]
         );
-        assert_json_include!(actual: diff, expected: expected);
     }
 
     #[test]
@@ -378,11 +484,52 @@ mod tests {
 
         let diff = json_obj_diff(&obj1, &obj2).unwrap();
 
-        let expected = json!(
-            {
-                "vec": [2, {}]  // Second elem is empty bacause it is unchanged
-            }
+        // No operation for the second elem, because it is unchanged
+        assert_eq!(
+            diff,
+            vec![PatchOp::Replace {
+                path: String::from("/vec/0"),
+                value: json!(2)
+            }]
         );
-        assert_json_include!(actual: diff, expected: expected);
+        assert_round_trip(&obj1, &obj2);
+    }
+
+    #[test]
+    fn json_obj_diff_escapes_keys() {
+        let obj1 = json!({ "a/b": { "c~d": 1 } });
+        let obj2 = json!({ "a/b": { "c~d": 2 } });
+
+        let diff = json_obj_diff(&obj1, &obj2).unwrap();
+
+        assert_eq!(
+            diff,
+            vec![PatchOp::Replace {
+                path: String::from("/a~1b/c~0d"),
+                value: json!(2)
+            }]
+        );
+        assert_round_trip(&obj1, &obj2);
+    }
+
+    #[test]
+    fn diff_serializes_as_json_patch() {
+        let diff = json_obj_diff(&json!({ "vec": [] }), &json!({ "vec": [null] })).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&diff).unwrap(),
+            json!([{ "op": "add", "path": "/vec/0", "value": null }])
+        );
+    }
+
+    #[test]
+    fn apply_diff_to_missing_path_fails() {
+        let doc = json!({ "foo": 1 });
+        let diff = vec![PatchOp::Replace {
+            path: String::from("/bar/baz"),
+            value: json!(2),
+        }];
+
+        assert!(apply_diff(&doc, &diff).is_err());
     }
 }
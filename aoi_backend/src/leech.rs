@@ -0,0 +1,229 @@
+use crate::common::VP;
+use crate::error::AoiError;
+use crate::faction::Faction;
+use crate::gamephase::PlayerId;
+use crate::map::{self, Map, Pos};
+use crate::Result;
+
+use serde::Serialize;
+
+/// An offer to gain power because another player built next to the player's buildings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct LeechOffer {
+    pub player_id: PlayerId, // The player who may gain power
+    pub builder: PlayerId,
+    pub power: u32,
+}
+
+impl LeechOffer {
+    /// Accepting costs one VP less than the power gained.
+    pub fn vp_cost(&self) -> VP {
+        VP(self.power.saturating_sub(1))
+    }
+}
+
+/// Computes the offers that follow from `builder` building or upgrading at `pos`.
+///
+/// Every other player is offered the summed power value of their own buildings next to
+/// `pos`. The amount is capped by how much power the player can gain and by how many VP
+/// the player can pay. Offers are ordered in turn direction starting after the builder.
+pub fn leech_offers(
+    map: &Map,
+    factions: &[Faction],
+    builder: PlayerId,
+    pos: Pos,
+) -> Vec<LeechOffer> {
+    let num_players = factions.len();
+
+    (1..num_players)
+        .map(|i| (builder + i) % num_players)
+        .filter_map(|player_id| {
+            let adjacent_power: u32 = map::neighbors(pos)
+                .into_iter()
+                .filter_map(|(r, c)| match map[r][c].building {
+                    Some((owner, building)) if owner == player_id => Some(building.power_value()),
+                    _ => None,
+                })
+                .sum();
+
+            let faction = &factions[player_id];
+            let power = adjacent_power
+                .min(faction.power().gain_limit())
+                .min(faction.vp().0 + 1);
+
+            (power > 0).then_some(LeechOffer {
+                player_id,
+                builder,
+                power,
+            })
+        })
+        .collect()
+}
+
+/// Offers waiting for the offered players to accept or decline.
+#[derive(Clone, Default)]
+pub struct LeechQueue {
+    pending: Vec<LeechOffer>,
+}
+
+impl LeechQueue {
+    pub fn new() -> Self {
+        LeechQueue {
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, offers: Vec<LeechOffer>) {
+        self.pending.extend(offers);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn pending(&self) -> &Vec<LeechOffer> {
+        &self.pending
+    }
+
+    /// The oldest offer made to the player, if any.
+    pub fn offer_for(&self, player_id: PlayerId) -> Option<&LeechOffer> {
+        self.pending.iter().find(|o| o.player_id == player_id)
+    }
+
+    /// Resolves the oldest offer made to the player. An accepted offer gains the player
+    /// power and costs VP according to how much power was actually gained. Some races gain
+    /// more power or pay less VP.
+    pub fn decide(
+        &mut self,
+        player_id: PlayerId,
+        accept: bool,
+        factions: &mut [Faction],
+    ) -> Result<LeechOffer> {
+        let idx = self
+            .pending
+            .iter()
+            .position(|o| o.player_id == player_id)
+            .ok_or(AoiError::NoLeechOffer { player_id })?;
+        let offer = self.pending.remove(idx);
+
+        if accept {
+            let faction = &mut factions[player_id];
+            let race = faction.race();
+            let gained = faction.gain_power(offer.power);
+            faction.gain_power(race.leech_power_bonus());
+            faction.lose_vp(VP(gained
+                .saturating_sub(1)
+                .saturating_sub(race.leech_vp_discount().0)));
+        }
+
+        Ok(offer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::building::Building;
+    use crate::common::Color;
+    use crate::map::{open_map, MapId};
+    use crate::race::Race;
+
+    fn create_factions(num_players: usize) -> Vec<Faction> {
+        (0..num_players)
+            .map(|_| Faction::new(&Race::Raceless, &Color::Colorless))
+            .collect()
+    }
+
+    #[test]
+    fn neighbor_is_offered_summed_power_values() {
+        let mut map = open_map(MapId::Debug);
+        let factions = create_factions(3);
+        map[1][1].building = Some((1, Building::Guild));
+        map[1][3].building = Some((1, Building::Workshop));
+        map[1][2].building = Some((0, Building::Workshop));
+
+        let offers = leech_offers(&map, &factions, 0, (1, 2));
+
+        assert_eq!(
+            offers,
+            vec![LeechOffer {
+                player_id: 1,
+                builder: 0,
+                power: 3
+            }]
+        );
+        assert_eq!(offers[0].vp_cost(), VP(2));
+    }
+
+    #[test]
+    fn own_and_distant_buildings_give_no_offers() {
+        let mut map = open_map(MapId::Debug);
+        let factions = create_factions(3);
+        map[1][1].building = Some((0, Building::Palace));
+        map[1][5].building = Some((2, Building::Palace));
+
+        assert!(leech_offers(&map, &factions, 0, (1, 2)).is_empty());
+    }
+
+    #[test]
+    fn offers_are_capped_by_gain_limit() {
+        let mut map = open_map(MapId::Debug);
+        let mut factions = create_factions(2);
+        factions[1].gain_power(5 + 11); // Bowls end up as [0, 1, 11]
+        map[1][1].building = Some((1, Building::Palace));
+
+        let offers = leech_offers(&map, &factions, 0, (1, 2));
+
+        assert_eq!(offers[0].power, 1);
+    }
+
+    #[test]
+    fn offers_are_ordered_after_builder() {
+        let mut map = open_map(MapId::Debug);
+        let factions = create_factions(4);
+        map[1][1].building = Some((0, Building::Workshop));
+        map[1][3].building = Some((1, Building::Workshop));
+        map[2][2].building = Some((3, Building::Workshop));
+
+        let offers = leech_offers(&map, &factions, 2, (1, 2));
+
+        let order: Vec<PlayerId> = offers.iter().map(|o| o.player_id).collect();
+        assert_eq!(order, vec![3, 0, 1]);
+    }
+
+    #[test]
+    fn accepting_gains_power_and_costs_vp() {
+        let mut factions = create_factions(2);
+        let mut queue = LeechQueue::new();
+        queue.push(vec![LeechOffer {
+            player_id: 1,
+            builder: 0,
+            power: 3,
+        }]);
+
+        queue.decide(1, true, &mut factions).unwrap();
+
+        assert!(queue.is_empty());
+        assert_eq!(factions[1].vp(), VP(18));
+        assert_eq!(factions[1].power().amount(2), 10);
+    }
+
+    #[test]
+    fn declining_changes_nothing() {
+        let mut factions = create_factions(2);
+        let mut queue = LeechQueue::new();
+        queue.push(vec![LeechOffer {
+            player_id: 1,
+            builder: 0,
+            power: 3,
+        }]);
+
+        assert!(queue.decide(0, false, &mut factions).is_err()); // No offer to player 0
+        queue.decide(1, false, &mut factions).unwrap();
+
+        assert!(queue.is_empty());
+        assert_eq!(factions[1].vp(), VP(20));
+        assert_eq!(factions[1].power().amount(2), 7);
+    }
+}
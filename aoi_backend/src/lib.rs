@@ -1,17 +1,25 @@
 // Public modules
+pub mod actionlog;
 pub mod bonustile;
 pub mod bookaction;
+pub mod bot;
 pub mod building;
+pub mod colorbonus;
 pub mod common;
 pub mod error;
 pub mod faction;
+pub mod game;
 pub mod gamephase;
 pub mod helpers;
+pub mod leech;
 pub mod map;
+pub mod movegen;
 pub mod power;
 pub mod pregame;
 pub mod race;
+pub mod reachability;
 pub mod resources;
 pub mod scoringtile;
+pub mod terraform;
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub type Result<T> = std::result::Result<T, error::AoiError>;
@@ -1,19 +1,62 @@
+use std::fmt;
+
 use enum_iterator::Sequence;
+use itertools::Itertools;
+use serde::{Serialize, Serializer};
 
-use crate::{common::Color, error::create_error, Result};
+use crate::{
+    building::Building, common::Color, error::AoiError, gamephase::PlayerId, resources::Power,
+    Result,
+};
 
 pub const MAP_HEIGHT: usize = 9;
 pub const MAP_WIDTH: usize = 13;
 
-pub type Map = Vec<Vec<Hex>>;
+// Fixed size, so that copying the map doesn't allocate
+pub type Map = [[Hex; MAP_WIDTH]; MAP_HEIGHT];
 pub type Pos = (usize, usize);
 
+#[derive(Clone, Copy, Serialize)]
 pub struct Hex {
-    pub name: Option<String>,
+    pub name: Option<HexName>,
     pub terrain: Terrain,
+    pub building: Option<(PlayerId, Building)>,
+}
+
+/// Name of a land hex, e.g. "C4". Land hexes are numbered from the left in every row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HexName {
+    row: char,
+    number: u32,
+}
+
+impl fmt::Display for HexName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.row, self.number)
+    }
+}
+
+impl Serialize for HexName {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
 }
 
-#[derive(PartialEq, Sequence)]
+// Bridges are built for power, and each player only has a few of them
+pub const BRIDGE_COST: Power = Power(3);
+pub const MAX_BRIDGES: usize = 3;
+
+/// Connects two hexes of a player across water, as if they were neighbors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Bridge {
+    pub player_id: PlayerId,
+    pub ends: (Pos, Pos),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Sequence, Serialize)]
 pub enum Terrain {
     Land(Color),
     Water,
@@ -25,8 +68,8 @@ pub enum MapId {
 }
 
 pub fn open_map(id: MapId) -> Map {
-    const BASE_MAP: &'static str = include_str!("../assets/base_map.gamemap");
-    const DEBUG_MAP: &'static str = include_str!("../assets/debug_map.gamemap");
+    const BASE_MAP: &str = include_str!("../assets/base_map.gamemap");
+    const DEBUG_MAP: &str = include_str!("../assets/debug_map.gamemap");
 
     let map: &str = match id {
         MapId::Base => BASE_MAP,
@@ -38,22 +81,31 @@ pub fn open_map(id: MapId) -> Map {
 
 fn open_map_from_str(input: &str) -> Result<Map> {
     let mut row_name_gen = "ABCDEFGHIJ".chars();
-    let mut res: Map = Vec::with_capacity(MAP_HEIGHT);
+    let mut rows: Vec<[Hex; MAP_WIDTH]> = Vec::with_capacity(MAP_HEIGHT);
 
     for row in input.split('\n') {
         let row_name = match row_name_gen.next() {
             Some(x) => x,
-            None => return Err(create_error("Too many rows")),
+            None => {
+                return Err(AoiError::InvalidMap {
+                    reason: String::from("Too many rows"),
+                })
+            }
         };
-        let hexes: Vec<Hex> = parse_row(&row, row_name)?;
-        res.push(hexes);
+        rows.push(parse_row(row, row_name)?);
     }
 
-    Ok(res)
+    rows.try_into()
+        .map_err(|rows: Vec<_>| AoiError::InvalidMap {
+            reason: format!("{} rows (expects {})", rows.len(), MAP_HEIGHT),
+        })
 }
 
-fn parse_row(input: &str, row_name: char) -> Result<Vec<Hex>> {
-    let mut hex_name_gen = (1..).map(|n| format!("{}{}", row_name, n));
+fn parse_row(input: &str, row_name: char) -> Result<[Hex; MAP_WIDTH]> {
+    let mut hex_name_gen = (1..).map(|number| HexName {
+        row: row_name,
+        number,
+    });
 
     let row: Result<Vec<Hex>> = input
         .split(",")
@@ -67,34 +119,169 @@ fn parse_row(input: &str, row_name: char) -> Result<Vec<Hex>> {
                 "G" => Ok(Terrain::Land(Color::Green)),
                 "S" => Ok(Terrain::Land(Color::Gray)),
                 "R" => Ok(Terrain::Land(Color::Red)),
-                x => return Err(create_error(&format!("Invalid symbol '{}'", x))),
+                x => {
+                    return Err(AoiError::InvalidMap {
+                        reason: format!("Invalid symbol '{}'", x),
+                    })
+                }
             }
-            .and_then(|t| match t {
-                Terrain::Water => Ok(Hex {
+            .map(|t| match t {
+                Terrain::Water => Hex {
                     name: None,
                     terrain: t,
-                }),
-                _ => Ok(Hex {
+                    building: None,
+                },
+                _ => Hex {
                     name: hex_name_gen.next(),
                     terrain: t,
-                }),
+                    building: None,
+                },
             })
         })
         .collect();
 
-    row.and_then(|v| match v.len() {
-        MAP_WIDTH => Ok(v),
-        _ => Err(create_error(&format!(
-            "Incorrect width of map at row '{}' (expects {})",
-            row_name, MAP_WIDTH
-        ))),
+    row.and_then(|v| {
+        v.try_into().map_err(|_| AoiError::InvalidMap {
+            reason: format!(
+                "Incorrect width of map at row '{}' (expects {})",
+                row_name, MAP_WIDTH
+            ),
+        })
     })
 }
 
+/// Returns all positions inside the map that share an edge with `pos`.
+///
+/// The map is laid out as rows of hexes where every odd row is shifted half a
+/// hex to the right, so the diagonal neighbors depend on the parity of the row.
+pub fn neighbors(pos: Pos) -> Vec<Pos> {
+    let (row, col) = (pos.0 as isize, pos.1 as isize);
+    let offsets: [(isize, isize); 6] = if row % 2 == 0 {
+        [(0, -1), (0, 1), (-1, -1), (-1, 0), (1, -1), (1, 0)]
+    } else {
+        [(0, -1), (0, 1), (-1, 0), (-1, 1), (1, 0), (1, 1)]
+    };
+
+    offsets
+        .iter()
+        .map(|(dr, dc)| (row + dr, col + dc))
+        .filter(|&(r, c)| r >= 0 && c >= 0)
+        .map(|(r, c)| (r as usize, c as usize))
+        .filter(|&p| inside_bounds(p))
+        .collect()
+}
+
+pub fn inside_bounds(pos: Pos) -> bool {
+    pos.0 < MAP_HEIGHT && pos.1 < MAP_WIDTH
+}
+
+pub fn is_neighbor(a: Pos, b: Pos) -> bool {
+    distance(a, b) == 1
+}
+
+/// Number of steps between two hexes when walking from hex to hex.
+pub fn distance(a: Pos, b: Pos) -> usize {
+    let (ax, ay, az) = to_cube(a);
+    let (bx, by, bz) = to_cube(b);
+
+    [(ax - bx).abs(), (ay - by).abs(), (az - bz).abs()]
+        .into_iter()
+        .max()
+        .unwrap() as usize
+}
+
+/// All positions inside the map at exactly `radius` steps from `center`.
+pub fn ring(center: Pos, radius: usize) -> impl Iterator<Item = Pos> {
+    all_positions().filter(move |&p| distance(center, p) == radius)
+}
+
+/// All positions inside the map at most `radius` steps from `center`, including `center` itself.
+pub fn area(center: Pos, radius: usize) -> impl Iterator<Item = Pos> {
+    all_positions().filter(move |&p| distance(center, p) <= radius)
+}
+
+/// Positions of all buildings owned by the player.
+pub fn buildings_of(map: &Map, player_id: PlayerId) -> Vec<Pos> {
+    all_positions()
+        .filter(|&(r, c)| matches!(map[r][c].building, Some((owner, _)) if owner == player_id))
+        .collect()
+}
+
+/// Number of buildings in the player's largest group of buildings connected through
+/// neighboring hexes and the player's bridges.
+pub fn largest_connected_area(map: &Map, bridges: &[Bridge], player_id: PlayerId) -> usize {
+    let buildings = buildings_of(map, player_id);
+    let connected = |a: Pos, b: Pos| {
+        is_neighbor(a, b)
+            || bridges
+                .iter()
+                .any(|br| br.player_id == player_id && (br.ends == (a, b) || br.ends == (b, a)))
+    };
+
+    let mut visited = vec![false; buildings.len()];
+    let mut largest = 0;
+    for start in 0..buildings.len() {
+        if visited[start] {
+            continue;
+        }
+
+        visited[start] = true;
+        let mut stack = vec![start];
+        let mut size = 0;
+        while let Some(i) = stack.pop() {
+            size += 1;
+            for j in 0..buildings.len() {
+                if !visited[j] && connected(buildings[i], buildings[j]) {
+                    visited[j] = true;
+                    stack.push(j);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+
+    largest
+}
+
+/// Whether a bridge may join the two hexes: both are land, two steps apart, and every hex
+/// between them is water.
+pub fn can_bridge(map: &Map, (a, b): (Pos, Pos)) -> bool {
+    let is_land = |(r, c): Pos| matches!(map[r][c].terrain, Terrain::Land(_));
+
+    inside_bounds(a)
+        && inside_bounds(b)
+        && distance(a, b) == 2
+        && is_land(a)
+        && is_land(b)
+        && neighbors(a)
+            .into_iter()
+            .filter(|&p| is_neighbor(p, b))
+            .all(|(r, c)| map[r][c].terrain == Terrain::Water)
+}
+
+/// Whether any hex next to `pos` has a building owned by another player.
+pub fn has_other_neighbor(map: &Map, pos: Pos, player_id: PlayerId) -> bool {
+    neighbors(pos)
+        .into_iter()
+        .any(|(r, c)| matches!(map[r][c].building, Some((owner, _)) if owner != player_id))
+}
+
+pub fn all_positions() -> impl Iterator<Item = Pos> {
+    (0..MAP_HEIGHT).cartesian_product(0..MAP_WIDTH)
+}
+
+// Converts offset coordinates to cube coordinates, where distances are easy to compute.
+fn to_cube(pos: Pos) -> (isize, isize, isize) {
+    let (row, col) = (pos.0 as isize, pos.1 as isize);
+    let x = col - (row - (row & 1)) / 2;
+    let z = row;
+
+    (x, -x - z, z)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use itertools::Itertools;
 
     #[test]
     fn import_basemap() {
@@ -160,4 +347,162 @@ mod tests {
         assert!(map.len() == MAP_HEIGHT);
         assert!(map.into_iter().all(|row| row.len() == MAP_WIDTH));
     }
+
+    #[test]
+    fn land_hexes_are_named_by_row() {
+        let map = open_map(MapId::Base);
+
+        let names = map[0]
+            .iter()
+            .filter_map(|hex| hex.name)
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names.first().map(String::as_str), Some("A1"));
+        assert_eq!(names.last(), Some(&format!("A{}", names.len())));
+        assert_eq!(serde_json::to_value(map[0][0].name).unwrap(), "A1");
+    }
+
+    #[test]
+    fn neighbors_on_even_row() {
+        let mut actual = neighbors((2, 4));
+        actual.sort();
+
+        assert_eq!(actual, vec![(1, 3), (1, 4), (2, 3), (2, 5), (3, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn neighbors_on_odd_row() {
+        let mut actual = neighbors((3, 4));
+        actual.sort();
+
+        assert_eq!(actual, vec![(2, 4), (2, 5), (3, 3), (3, 5), (4, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn neighbors_are_clipped_at_map_border() {
+        let mut corner = neighbors((0, 0));
+        corner.sort();
+        assert_eq!(corner, vec![(0, 1), (1, 0)]);
+
+        let mut other_corner = neighbors((MAP_HEIGHT - 1, MAP_WIDTH - 1));
+        other_corner.sort();
+        assert_eq!(
+            other_corner,
+            vec![
+                (MAP_HEIGHT - 2, MAP_WIDTH - 2),
+                (MAP_HEIGHT - 2, MAP_WIDTH - 1),
+                (MAP_HEIGHT - 1, MAP_WIDTH - 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbor_relation_is_symmetric() {
+        for pos in all_positions() {
+            for n in neighbors(pos) {
+                assert!(neighbors(n).contains(&pos));
+                assert!(is_neighbor(pos, n));
+            }
+        }
+    }
+
+    #[test]
+    fn distance_between_hexes() {
+        assert_eq!(distance((4, 4), (4, 4)), 0);
+        assert_eq!(distance((4, 4), (4, 7)), 3);
+        assert_eq!(distance((4, 4), (6, 4)), 2);
+        assert_eq!(distance((0, 0), (8, 0)), 8);
+        assert_eq!(distance((0, 0), (8, 4)), 8);
+        assert_eq!(distance((0, 0), (8, 5)), 9);
+    }
+
+    #[test]
+    fn ring_sizes_away_from_border() {
+        let center = (4, 6);
+
+        assert_eq!(ring(center, 0).collect_vec(), vec![center]);
+        assert_eq!(ring(center, 1).count(), 6);
+        assert_eq!(ring(center, 2).count(), 12);
+        assert_eq!(ring(center, 3).count(), 18);
+    }
+
+    #[test]
+    fn area_is_union_of_rings() {
+        let center = (4, 6);
+
+        let from_rings = (0..=3).flat_map(|r| ring(center, r)).sorted().collect_vec();
+        let from_area = area(center, 3).sorted().collect_vec();
+
+        assert_eq!(from_area, from_rings);
+        assert_eq!(from_area.len(), 1 + 6 + 12 + 18);
+    }
+
+    #[test]
+    fn buildings_and_neighbors_by_owner() {
+        let mut map = open_map(MapId::Debug);
+        map[1][1].building = Some((0, Building::Workshop));
+        map[5][5].building = Some((0, Building::Guild));
+        map[1][2].building = Some((1, Building::Workshop));
+
+        assert_eq!(buildings_of(&map, 0), vec![(1, 1), (5, 5)]);
+        assert_eq!(buildings_of(&map, 1), vec![(1, 2)]);
+        assert!(has_other_neighbor(&map, (1, 1), 0));
+        assert!(!has_other_neighbor(&map, (5, 5), 0));
+        assert!(!has_other_neighbor(&map, (1, 3), 1));
+    }
+
+    #[test]
+    fn largest_area_follows_neighbors_and_bridges() {
+        let mut map = open_map(MapId::Debug);
+        for pos in [(1, 1), (1, 2), (2, 1), (5, 5), (5, 6), (1, 8)] {
+            map[pos.0][pos.1].building = Some((0, Building::Workshop));
+        }
+        map[1][3].building = Some((1, Building::Workshop));
+
+        assert_eq!(largest_connected_area(&map, &[], 0), 3);
+        assert_eq!(largest_connected_area(&map, &[], 1), 1);
+        assert_eq!(largest_connected_area(&map, &[], 2), 0);
+
+        let bridge = Bridge {
+            player_id: 0,
+            ends: ((5, 5), (2, 1)),
+        };
+        assert_eq!(largest_connected_area(&map, &[bridge], 0), 5);
+        assert_eq!(
+            largest_connected_area(
+                &map,
+                &[Bridge {
+                    player_id: 1,
+                    ..bridge
+                }],
+                0
+            ),
+            3
+        );
+    }
+
+    #[test]
+    fn bridges_only_cross_water() {
+        let map = open_map(MapId::Debug);
+
+        assert!(can_bridge(&map, ((1, 5), (1, 7))));
+        assert!(can_bridge(&map, ((3, 2), (5, 2))));
+        // Land in between
+        assert!(!can_bridge(&map, ((1, 1), (1, 3))));
+        // Too far apart
+        assert!(!can_bridge(&map, ((1, 4), (1, 7))));
+        // Water at one end
+        assert!(!can_bridge(&map, ((1, 5), (0, 7))));
+    }
+
+    #[test]
+    fn ring_of_radius_one_equals_neighbors() {
+        let pos = (0, 5);
+
+        assert_eq!(
+            ring(pos, 1).sorted().collect_vec(),
+            neighbors(pos).into_iter().sorted().collect_vec()
+        );
+    }
 }
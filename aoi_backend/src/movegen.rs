@@ -0,0 +1,410 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::bookaction::BookActionEffect;
+use crate::building::Building;
+use crate::common::{Color, Discipline};
+use crate::game::{Action, Game};
+use crate::gamephase::PlayerId;
+use crate::power::Conversion;
+use crate::resources::{Books, Coins, Resources, Scholars, Tools};
+use crate::{map, reachability};
+
+/// The player whose decision the game is waiting for. Pending power offers are decided
+/// on before the player to move continues.
+pub fn player_to_act(game: &Game) -> Option<PlayerId> {
+    match game.pending_leech().first() {
+        Some(offer) => Some(offer.player_id),
+        None => game.player_to_move(),
+    }
+}
+
+/// Every legal action of the player to act, see `player_to_act`.
+///
+/// Power conversions are listed one unit at a time, since larger conversions are the
+/// same as several small ones in a row. Undo, redo and abort are left out as they only
+/// return to earlier states.
+///
+/// Each candidate action is tried on its own copy of the game, which is the main cost
+/// of every bot decision. The copies leave out the undo history of the turn.
+pub fn legal_actions(game: &Game) -> Vec<Action> {
+    legal_successors(game)
+        .into_iter()
+        .map(|(action, _)| action)
+        .collect()
+}
+
+/// The legal actions along with the game after each of them, see `legal_actions`.
+pub fn legal_successors(game: &Game) -> Vec<(Action, Game)> {
+    let Some(player_id) = player_to_act(game) else {
+        return Vec::new();
+    };
+
+    candidates(game, player_id)
+        .into_iter()
+        .filter_map(|action| {
+            let mut after = game.lookahead();
+            after.apply(player_id, &action).ok()?;
+            Some((action, after))
+        })
+        .collect()
+}
+
+/// Applies a legal action of the player to act, picked uniformly at random. Much cheaper
+/// than choosing from `legal_actions`, since rejected candidates leave the game unchanged
+/// and need no copy of it.
+pub fn apply_random<R: Rng + ?Sized>(game: &mut Game, rng: &mut R) -> Option<Action> {
+    let player_id = player_to_act(game)?;
+
+    let mut actions = candidates(game, player_id);
+    actions.shuffle(rng);
+    actions
+        .into_iter()
+        .find(|action| game.apply(player_id, action).is_ok())
+}
+
+// Actions that may be legal, to be checked by applying them. Hexes out of reach are left
+// out up front, as they make up most of the map.
+fn candidates(game: &Game, player_id: PlayerId) -> Vec<Action> {
+    let state = game.state();
+    match game {
+        Game::BuildingPlacement(_) => map::all_positions()
+            .map(|pos| Action::Place { pos })
+            .collect(),
+        Game::Action(_) if !game.pending_leech().is_empty() => vec![
+            Action::Leech { accept: true },
+            Action::Leech { accept: false },
+        ],
+        Game::Action(_) => {
+            let faction = &state.factions()[player_id];
+            let own_buildings = map::buildings_of(state.map(), player_id);
+            let reach = reachability::reachable_by(state.map(), &own_buildings, faction);
+            let reachable = map::all_positions()
+                .filter(|&pos| reach.contains(pos))
+                .collect::<Vec<_>>();
+
+            let builds = reachable.iter().map(|&pos| Action::Build { pos });
+            let terraforms = reachable.iter().flat_map(|&pos| {
+                enum_iterator::all::<Color>().map(move |to| Action::Terraform { pos, to })
+            });
+            let upgrades = own_buildings.iter().flat_map(|&pos| {
+                enum_iterator::all::<Building>().map(move |to| Action::Upgrade { pos, to })
+            });
+            let bridges = own_buildings.iter().flat_map(|&from| {
+                map::ring(from, 2)
+                    .filter(move |&to| map::can_bridge(state.map(), (from, to)))
+                    .map(move |to| Action::BuildBridge { from, to })
+            });
+            let conversions = [
+                Conversion {
+                    burn: 1,
+                    ..Default::default()
+                },
+                Conversion {
+                    coins: Coins(1),
+                    ..Default::default()
+                },
+                Conversion {
+                    tools: Tools(1),
+                    ..Default::default()
+                },
+                Conversion {
+                    scholars: Scholars(1),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .chain(enum_iterator::all::<Discipline>().map(|disc| Conversion {
+                books: Some((disc, Books(1))),
+                ..Default::default()
+            }))
+            .map(Action::ConvertPower);
+            let passes = state
+                .bonus_tiles()
+                .iter()
+                .map(|&tile| Action::Pass {
+                    bonus_tile: Some(tile),
+                })
+                .chain([Action::Pass { bonus_tile: None }]);
+
+            builds
+                .chain(terraforms)
+                .chain(upgrades)
+                .chain(bridges)
+                .chain(book_actions(game, player_id, &reachable))
+                .chain([Action::AdvanceSailing, Action::AdvanceDigging])
+                .chain(conversions)
+                .chain([Action::EndTurn])
+                .chain(passes)
+                .collect()
+        }
+        Game::Cleanup(_) => map::all_positions()
+            .map(|pos| Action::Dig { pos })
+            .chain([Action::DeclineSpades])
+            .collect(),
+        Game::FinalScoring(_) => Vec::new(),
+    }
+}
+
+// Book actions the player can pay for in any mix of held colors, on every target their
+// effect applies to
+fn book_actions(game: &Game, player_id: PlayerId, reachable: &[map::Pos]) -> Vec<Action> {
+    let state = game.state();
+    let faction = &state.factions()[player_id];
+
+    let mut actions = Vec::new();
+    for (idx, book_action) in state.book_actions().iter().enumerate() {
+        if state.book_action_taken(idx) {
+            continue;
+        }
+
+        let discs = enum_iterator::all::<Discipline>().collect::<Vec<_>>();
+        let payments = payments(&faction.resources(), &discs, book_action.cost().0);
+        let targets: Vec<(Option<Discipline>, Option<map::Pos>)> = match book_action.effect() {
+            BookActionEffect::DiscStep => enum_iterator::all::<Discipline>()
+                .map(|disc| (Some(disc), None))
+                .collect(),
+            BookActionEffect::UpgradeToGuild => map::buildings_of(state.map(), player_id)
+                .into_iter()
+                .map(|pos| (None, Some(pos)))
+                .collect(),
+            BookActionEffect::Spades => reachable.iter().map(|&pos| (None, Some(pos))).collect(),
+            _ => vec![(None, None)],
+        };
+
+        for payment in &payments {
+            actions.extend(targets.iter().map(|&(disc, pos)| Action::BookAction {
+                idx,
+                payment: payment.clone(),
+                disc,
+                pos,
+            }));
+        }
+    }
+
+    actions
+}
+
+// The ways of paying `cost` books out of those held in the disciplines, in their order
+fn payments(held: &Resources, discs: &[Discipline], cost: u32) -> Vec<Vec<(Discipline, Books)>> {
+    let Some((&disc, rest)) = discs.split_first() else {
+        return if cost == 0 {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    };
+
+    (0..=cost.min(held.books(disc).0))
+        .flat_map(|amount| {
+            payments(held, rest, cost - amount)
+                .into_iter()
+                .map(move |mut payment| {
+                    if amount > 0 {
+                        payment.insert(0, (disc, Books(amount)));
+                    }
+                    payment
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::actionlog;
+    use crate::bookaction::BookAction;
+    use crate::faction::Faction;
+    use crate::gamephase::actionphase::ActionPhase;
+    use crate::gamephase::buildingplacement::BuildingPlacer;
+    use crate::gamephase::GameState;
+    use crate::race::Race;
+    use crate::resources::Power;
+
+    use parameterized::parameterized;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn placements_match_valid_placement(num_players: u32) {
+        let game = actionlog::test_game(num_players);
+        let Game::BuildingPlacement(placer) = &game else {
+            panic!("Game does not start with placement");
+        };
+
+        let actions = legal_actions(&game);
+
+        let expected = map::all_positions()
+            .filter(|&pos| placer.valid_placement(0, pos))
+            .map(|pos| Action::Place { pos })
+            .collect::<Vec<_>>();
+        assert!(!actions.is_empty());
+        assert_eq!(actions, expected);
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn random_legal_actions_finish_the_game(num_players: u32) {
+        let mut game = actionlog::test_game(num_players);
+        let mut rng = StdRng::seed_from_u64(num_players as u64);
+
+        // Ending the turn or passing is always possible, so games can't get stuck
+        for _ in 0..10_000 {
+            let Some(player_id) = player_to_act(&game) else {
+                break;
+            };
+            let actions = legal_actions(&game);
+            let action = actions.choose(&mut rng).unwrap();
+            game.apply(player_id, action).unwrap();
+        }
+
+        assert!(game.is_finished());
+        assert!(legal_actions(&game).is_empty());
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn random_actions_are_legal(num_players: u32) {
+        let mut game = actionlog::test_game(num_players);
+        let mut rng = StdRng::seed_from_u64(num_players as u64);
+
+        while !game.is_finished() {
+            let legal = legal_actions(&game);
+            let action = apply_random(&mut game, &mut rng).unwrap();
+            assert!(legal.contains(&action));
+        }
+
+        assert_eq!(apply_random(&mut game, &mut rng), None);
+    }
+
+    #[test]
+    fn leech_is_decided_before_the_turn_continues() {
+        let mut game = actionlog::test_game(2);
+        actionlog::place_initial_buildings(&mut game);
+
+        // Upgrade the first possible building, which offers power to any neighbors
+        loop {
+            let player_id = player_to_act(&game).unwrap();
+            let actions = legal_actions(&game);
+            match actions
+                .iter()
+                .find(|action| matches!(action, Action::Upgrade { .. }))
+            {
+                Some(upgrade) => {
+                    game.apply(player_id, upgrade).unwrap();
+                    game.apply(player_id, &Action::EndTurn).unwrap();
+                }
+                None => game.apply(player_id, actions.last().unwrap()).unwrap(),
+            }
+            if !game.pending_leech().is_empty() {
+                break;
+            }
+        }
+
+        let offer = game.pending_leech()[0];
+        assert_eq!(player_to_act(&game), Some(offer.player_id));
+        assert_eq!(
+            legal_actions(&game),
+            vec![
+                Action::Leech { accept: true },
+                Action::Leech { accept: false }
+            ]
+        );
+    }
+
+    #[test]
+    fn books_are_paid_in_every_mix_of_held_colors() {
+        let held = Resources::from((Discipline::Law, Books(2)))
+            + &Resources::from((Discipline::Medicine, Books(1)));
+        let discs = enum_iterator::all::<Discipline>().collect::<Vec<_>>();
+
+        assert_eq!(
+            payments(&held, &discs, 2),
+            vec![
+                vec![
+                    (Discipline::Law, Books(1)),
+                    (Discipline::Medicine, Books(1))
+                ],
+                vec![(Discipline::Law, Books(2))],
+            ]
+        );
+        assert!(payments(&held, &discs, 4).is_empty());
+    }
+
+    #[test]
+    fn terraforms_and_book_actions_are_legal() {
+        let factions = (0..2)
+            .map(|_| {
+                let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+                faction.gain(&Resources::from((Discipline::Law, Books(1))));
+                faction
+            })
+            .collect();
+        let mut state = GameState::new(factions, map::open_map(map::MapId::Debug));
+        state.book_actions = vec![
+            BookAction::new(Books(1), BookActionEffect::DiscStep),
+            BookAction::new(Books(2), BookActionEffect::GainCoins),
+        ];
+        let mut game = Game::new(BuildingPlacer::from_state(state));
+        actionlog::place_initial_buildings(&mut game);
+
+        let actions = legal_actions(&game);
+
+        assert!(actions
+            .iter()
+            .any(|action| matches!(action, Action::Terraform { .. })));
+        let book_actions = actions
+            .iter()
+            .filter(|action| matches!(action, Action::BookAction { .. }))
+            .collect::<Vec<_>>();
+        // Only the discipline step is affordable, on any of the four tracks
+        assert_eq!(book_actions.len(), 4);
+        assert_eq!(
+            book_actions[0],
+            &Action::BookAction {
+                idx: 0,
+                payment: vec![(Discipline::Law, Books(1))],
+                disc: Some(Discipline::Banking),
+                pos: None,
+            }
+        );
+    }
+
+    #[test]
+    fn bridges_are_built_from_own_buildings() {
+        let factions = (0..2)
+            .map(|_| {
+                let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+                faction.gain(&Resources::from(Power(8)));
+                faction
+            })
+            .collect();
+        let mut state = GameState::new(factions, map::open_map(map::MapId::Debug));
+        state.map[3][2].building = Some((0, Building::Workshop));
+        let game = Game::Action(ActionPhase::new(state));
+
+        let bridges = legal_actions(&game)
+            .into_iter()
+            .filter(|action| matches!(action, Action::BuildBridge { .. }))
+            .collect::<Vec<_>>();
+
+        // Across the water row below
+        assert_eq!(
+            bridges,
+            vec![
+                Action::BuildBridge {
+                    from: (3, 2),
+                    to: (5, 1)
+                },
+                Action::BuildBridge {
+                    from: (3, 2),
+                    to: (5, 2)
+                },
+                Action::BuildBridge {
+                    from: (3, 2),
+                    to: (5, 3)
+                },
+            ]
+        );
+    }
+}
@@ -1,11 +1,12 @@
 use std::cmp::min;
 
-use crate::error::create_error;
+use crate::common::Discipline;
+use crate::error::AoiError;
 use crate::resources::{Books, Coins, Scholars, Tools};
 use crate::Result;
 
 use serde::ser::SerializeSeq;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Clone, Copy)]
 pub struct PowerBowls {
@@ -25,10 +26,22 @@ impl Serialize for PowerBowls {
     }
 }
 
+/// Conversions a player makes at once. Power is burnt first, after which power in bowl 3
+/// is spent on the resources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Conversion {
+    pub burn: u32,
+    pub coins: Coins,
+    pub tools: Tools,
+    pub scholars: Scholars,
+    pub books: Option<(Discipline, Books)>, // Books only come in one color per conversion
+}
+
 pub struct PowerConversion {
     state_before: PowerBowls,
     state_after: PowerBowls,
-    books_gained: Books,
+    books_gained: [Books; 4], // Indexed by Discipline
     scholars_gained: Scholars,
     tools_gained: Tools,
     coins_gained: Coins,
@@ -59,7 +72,11 @@ impl PowerBowls {
 
     pub fn spend(&mut self, amount: u32) -> Result<()> {
         if self.bowls[2] < amount {
-            return Err(create_error("Not enough power in bowl 3"));
+            return Err(AoiError::NotEnoughPower {
+                bowl: 3,
+                required: amount,
+                available: self.bowls[2],
+            });
         }
 
         self.bowls[0] += amount;
@@ -99,16 +116,20 @@ impl PowerConversion {
         Ok(())
     }
 
-    pub fn convert_to_books(&mut self, amount: Books) -> Result<()> {
+    pub fn convert_to_books(&mut self, disc: Discipline, amount: Books) -> Result<()> {
         self.state_after.spend(5 * amount.0)?;
-        self.books_gained.0 += amount.0;
+        self.books_gained[disc as usize] += amount;
 
         Ok(())
     }
 
     pub fn burn_power(&mut self, amount: u32) -> Result<()> {
         if self.state_after.bowls[1] < amount * 2 {
-            return Err(create_error("Not enough power in bowl 2"));
+            return Err(AoiError::NotEnoughPower {
+                bowl: 2,
+                required: amount * 2,
+                available: self.state_after.bowls[1],
+            });
         }
 
         self.state_after.bowls[1] -= amount * 2;
@@ -121,15 +142,17 @@ impl PowerConversion {
 pub fn start_conversion(bowls: PowerBowls) -> PowerConversion {
     PowerConversion {
         state_before: bowls,
-        state_after: bowls.clone(),
-        books_gained: Books(0),
+        state_after: bowls,
+        books_gained: [Books(0); 4],
         scholars_gained: Scholars(0),
         tools_gained: Tools(0),
         coins_gained: Coins(0),
     }
 }
 
-pub fn finish_conversion(conv: PowerConversion) -> (PowerBowls, Books, Scholars, Tools, Coins) {
+pub fn finish_conversion(
+    conv: PowerConversion,
+) -> (PowerBowls, [Books; 4], Scholars, Tools, Coins) {
     (
         conv.state_after,
         conv.books_gained,
@@ -258,7 +281,7 @@ mod tests {
         let mut conv = start_conversion(bowls);
 
         conv.convert_to_coins(Coins(1)).unwrap(); // 1 power
-        conv.convert_to_books(Books(2)).unwrap(); // 10 power
+        conv.convert_to_books(Discipline::Law, Books(2)).unwrap(); // 10 power
         conv.convert_to_tools(Tools(2)).unwrap(); // 6 power
         conv.convert_to_coins(Coins(2)).unwrap(); // 2 power
         conv.convert_to_scholars(Scholars(1)).unwrap(); // 5 power
@@ -267,7 +290,8 @@ mod tests {
         assert_eq!(bowls.amount(3), 1);
         assert_eq!(bowls.amount(2), 5);
         assert_eq!(bowls.amount(1), 24);
-        assert_eq!(books.0, 2);
+        assert_eq!(books[Discipline::Law as usize], Books(2));
+        assert_eq!(books[Discipline::Medicine as usize], Books(0));
         assert_eq!(scholars.0, 1);
         assert_eq!(tools.0, 2);
         assert_eq!(coins.0, 3);
@@ -285,7 +309,7 @@ mod tests {
         assert_eq!(bowls.amount(3), 0);
         assert_eq!(bowls.amount(2), 1);
         assert_eq!(bowls.amount(1), 3);
-        assert_eq!(books.0, 0);
+        assert_eq!(books, [Books(0); 4]);
         assert_eq!(scholars.0, 0);
         assert_eq!(tools.0, 1);
         assert_eq!(coins.0, 0);
@@ -1,48 +1,89 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::izip;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
 
 use crate::{
     bonustile::BonusTile,
-    bookaction::{new_game_random_book_actions, BookAction},
+    bookaction::{new_game_book_actions_from_rng, BookAction},
     common::Color,
-    error::create_error,
+    error::AoiError,
     faction::Faction,
+    gamephase::{buildingplacement::BuildingPlacer, GameState},
     map,
     race::Race,
     scoringtile, Result,
 };
 
 pub struct PreGame {
+    seed: u64,
     num_players: u32,
-    map: Vec<Vec<map::Hex>>,
+    map: map::Map,
     scoring_tiles: Vec<scoringtile::ScoringTile>,
-    faction_pool: Rc<FactionPool>,
+    faction_pool: Arc<FactionPool>,
     leftover_bonuses: Vec<BonusTile>,
     book_actions: Vec<BookAction>,
 }
 
-type FactionPool = Vec<(Race, BonusTile, Color)>;
+pub type FactionPool = Vec<(Race, BonusTile, Color)>;
 
 impl PreGame {
+    /// Sets up a game from a freshly drawn seed, see `new_seeded`.
     pub fn new_random(num_players: u32) -> Self {
-        let (faction_pool, leftover_bonuses) = gen_random_faction_pool();
+        Self::new_seeded(num_players, thread_rng().gen())
+    }
+
+    /// Sets up a game where the scoring tiles, book actions and faction pool are fully
+    /// determined by `seed`.
+    pub fn new_seeded(num_players: u32, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (faction_pool, leftover_bonuses) = gen_random_faction_pool(&mut rng);
 
         PreGame {
+            seed,
             num_players,
             map: map::open_map(map::MapId::Base),
-            scoring_tiles: scoringtile::new_game_random_tiles(),
-            faction_pool: Rc::new(faction_pool),
+            scoring_tiles: scoringtile::new_game_tiles_from_rng(&mut rng),
+            faction_pool: Arc::new(faction_pool),
             leftover_bonuses,
-            book_actions: new_game_random_book_actions(),
+            book_actions: new_game_book_actions_from_rng(&mut rng),
         }
     }
+
+    pub fn num_players(&self) -> u32 {
+        self.num_players
+    }
+
+    /// The race, bonus tile and color of each faction that may be selected, indexed as
+    /// in `FactionSelector::select`. The race abilities are placeholders, see `Race`.
+    pub fn faction_pool(&self) -> &FactionPool {
+        &self.faction_pool
+    }
+
+    /// The seed the game was set up from. Setting up a game with the same seed again
+    /// reproduces it.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Sets up the board with the selected factions, after which the initial buildings
+    /// are placed. Bonus tiles not taken by any player are available when passing.
+    pub fn finish(self, selector: &FactionSelector) -> Result<BuildingPlacer> {
+        let factions = selector.finish()?;
+
+        let mut state = GameState::new(factions, self.map);
+        state.scoring_tiles = self.scoring_tiles;
+        state.bonus_tiles = self.leftover_bonuses;
+        state.bonus_tiles.extend(selector.unselected_bonus_tiles());
+        state.book_actions = self.book_actions;
+
+        Ok(BuildingPlacer::from_state(state))
+    }
 }
 
 pub struct FactionSelector {
     selected: Vec<usize>,
-    faction_pool: Rc<FactionPool>,
+    faction_pool: Arc<FactionPool>,
     num_players: u32,
 }
 
@@ -57,10 +98,13 @@ impl FactionSelector {
 
     pub fn select(&mut self, idx: usize) -> Result<()> {
         if self.selected.len() >= self.num_players as usize {
-            return Err(create_error("All factions are already selected"));
+            return Err(AoiError::AllFactionsSelected);
+        }
+        if idx >= self.faction_pool.len() {
+            return Err(AoiError::NoSuchFaction { idx });
         }
         if self.selected.contains(&idx) {
-            return Err(create_error("Faction has already been selected"));
+            return Err(AoiError::FactionAlreadySelected { idx });
         }
 
         self.selected.push(idx);
@@ -68,35 +112,46 @@ impl FactionSelector {
         Ok(())
     }
 
+    /// Indices of the selected factions, in the order the players selected them.
+    pub fn selected(&self) -> &Vec<usize> {
+        &self.selected
+    }
+
     pub fn finish(&self) -> Result<Vec<Faction>> {
         if self.selected.len() != self.num_players as usize {
-            return Err(create_error("All players must have selected a faction"));
+            return Err(AoiError::FactionSelectionNotFinished);
         }
 
         let mut res = Vec::new();
         for idx in &self.selected {
-            // TODO: Deal with the bonus tile
-            let (race, _, color) = &(*self.faction_pool)[*idx];
-            res.push(Faction::new(race, color));
+            let (race, bonus_tile, color) = &(*self.faction_pool)[*idx];
+            let mut faction = Faction::new(race, color);
+            faction.set_bonus_tile(Some(*bonus_tile));
+            res.push(faction);
         }
 
         Ok(res)
     }
-}
 
-fn gen_random_faction_pool() -> (FactionPool, Vec<BonusTile>) {
-    let mut rng = thread_rng();
+    pub fn unselected_bonus_tiles(&self) -> Vec<BonusTile> {
+        (0..self.faction_pool.len())
+            .filter(|idx| !self.selected.contains(idx))
+            .map(|idx| self.faction_pool[idx].1)
+            .collect()
+    }
+}
 
+fn gen_random_faction_pool<R: Rng + ?Sized>(rng: &mut R) -> (FactionPool, Vec<BonusTile>) {
     let mut races: Vec<Race> = enum_iterator::all().collect();
-    races.shuffle(&mut rng);
+    races.shuffle(rng);
 
     let mut bonus_tiles: Vec<BonusTile> = enum_iterator::all().collect();
-    bonus_tiles.shuffle(&mut rng);
+    bonus_tiles.shuffle(rng);
     let bonus_tiles_pool: Vec<BonusTile> = bonus_tiles.as_slice()[..7].to_vec();
     let leftover_bonuses: Vec<BonusTile> = bonus_tiles.as_slice()[7..10].to_vec();
 
     let mut colors: Vec<Color> = enum_iterator::all().collect();
-    colors.shuffle(&mut rng);
+    colors.shuffle(rng);
 
     (
         izip!(races, bonus_tiles_pool, colors).collect(),
@@ -180,6 +235,40 @@ mod tests {
         assert!(selector.select(num_players as usize).is_err());
     }
 
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn selected_factions_hold_their_bonus_tile(num_players: u32) {
+        let pregame = PreGame::new_random(num_players);
+        let mut selector = FactionSelector::new(&pregame);
+
+        for i in 0..num_players {
+            selector.select(i as usize).unwrap();
+        }
+
+        let factions = selector.finish().unwrap();
+        for (i, faction) in factions.iter().enumerate() {
+            assert_eq!(faction.bonus_tile(), Some(pregame.faction_pool[i].1));
+        }
+    }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn unselected_bonus_tiles_are_available_in_game(num_players: u32) {
+        let pregame = PreGame::new_random(num_players);
+        let mut selector = FactionSelector::new(&pregame);
+        for i in 0..num_players {
+            selector.select(i as usize).unwrap();
+        }
+
+        let placer = pregame.finish(&selector).unwrap();
+
+        let state = placer.state();
+        assert_eq!(state.bonus_tiles().len(), 3 + 7 - num_players as usize);
+        assert_eq!(state.scoring_tiles().len(), 6);
+        assert_eq!(state.book_actions().len(), 3);
+        for faction in state.factions() {
+            assert!(!state.bonus_tiles().contains(&faction.bonus_tile().unwrap()));
+        }
+    }
+
     #[parameterized(num_players = { 2, 3, 4, 5 })]
     fn select_duplicate_faction(num_players: u32) {
         let pregame = PreGame::new_random(num_players);
@@ -189,4 +278,31 @@ mod tests {
 
         assert!(selector.select(0).is_err());
     }
+
+    #[parameterized(num_players = { 2, 3, 4, 5 })]
+    fn same_seed_gives_same_setup(num_players: u32) {
+        let a = PreGame::new_seeded(num_players, 1234);
+        let b = PreGame::new_seeded(num_players, a.seed());
+
+        assert!(a.faction_pool == b.faction_pool);
+        assert!(a.leftover_bonuses == b.leftover_bonuses);
+        assert!(a.scoring_tiles == b.scoring_tiles);
+        assert!(a.book_actions == b.book_actions);
+    }
+
+    #[test]
+    fn different_seeds_give_different_setups() {
+        let a = PreGame::new_seeded(4, 1);
+        let b = PreGame::new_seeded(4, 2);
+
+        assert!(a.faction_pool != b.faction_pool || a.scoring_tiles != b.scoring_tiles);
+    }
+
+    #[test]
+    fn select_faction_outside_pool() {
+        let pregame = PreGame::new_random(2);
+        let mut selector = FactionSelector::new(&pregame);
+
+        assert_eq!(selector.select(7), Err(AoiError::NoSuchFaction { idx: 7 }));
+    }
 }
@@ -1,19 +1,354 @@
 use enum_iterator::Sequence;
 use serde::Serialize;
 
-#[derive(Copy, Clone, PartialEq, Eq, Sequence, Serialize)]
+use crate::building::Building;
+use crate::common::VP;
+use crate::resources::{Coins, Power, Resources, Scholars};
+
+/// The race of a faction. Apart from the starting discipline steps and tools, every race
+/// has an ability hooking into one of the subsystems of the game.
+///
+/// The abilities below are placeholders and not taken from the rulebook. They give each
+/// race something to play differently with until the printed abilities are implemented.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Sequence, Serialize)]
 pub enum Race {
+    /// Gains an extra VP whenever the scoring tile of the round awards VP.
     Blessed,
+    /// Schools cost two coins less.
     Monks,
+    /// Accepting power costs one VP less.
     Felines,
+    /// Sails one water hex further.
     Navigators,
+    /// Gains two coins for every spade used.
     Goblins,
+    /// Guilds always cost as little as next to other players.
     Omar,
+    /// Gains an extra power from every accepted power offer.
     Illusionists,
+    /// Gains an extra scholar as income.
     Inventors,
+    /// Leftover scholars are worth a VP each at final scoring.
     Philosophers,
+    /// Digging costs one tool less per spade.
     Lizards,
+    /// Gains two extra power as income.
     Psychics,
+    /// Reaches land hexes two steps away by tunneling.
     Moles,
     Raceless, // For testing
 }
+
+impl Race {
+    pub fn award_bonus(&self) -> VP {
+        match self {
+            Race::Blessed => VP(1),
+            _ => VP(0),
+        }
+    }
+
+    pub fn upgrade_discount(&self, to: Building) -> Resources {
+        match (self, to) {
+            (Race::Monks, Building::School) => Resources::from(Coins(2)),
+            _ => Resources::none(),
+        }
+    }
+
+    pub fn always_has_neighbor(&self) -> bool {
+        *self == Race::Omar
+    }
+
+    pub fn leech_vp_discount(&self) -> VP {
+        match self {
+            Race::Felines => VP(1),
+            _ => VP(0),
+        }
+    }
+
+    pub fn leech_power_bonus(&self) -> u32 {
+        match self {
+            Race::Illusionists => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn sailing_bonus(&self) -> u32 {
+        match self {
+            Race::Navigators => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn tunnels(&self) -> bool {
+        *self == Race::Moles
+    }
+
+    pub fn coins_per_spade(&self) -> Coins {
+        match self {
+            Race::Goblins => Coins(2),
+            _ => Coins(0),
+        }
+    }
+
+    pub fn income_bonus(&self) -> Resources {
+        match self {
+            Race::Inventors => Resources::from(Scholars(1)),
+            Race::Psychics => Resources::from(Power(2)),
+            _ => Resources::none(),
+        }
+    }
+
+    pub fn vp_per_leftover_scholar(&self) -> VP {
+        match self {
+            Race::Philosophers => VP(1),
+            _ => VP(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::common::Color;
+    use crate::faction::Faction;
+    use crate::map::{self, MapId};
+
+    fn faction(race: Race) -> Faction {
+        Faction::new(&race, &Color::Colorless)
+    }
+
+    mod blessed {
+        use super::*;
+
+        use crate::gamephase::GameState;
+        use crate::scoringtile::{self, LeftSideGoal, ScoringEvent};
+
+        #[test]
+        fn scoring_tile_awards_extra_vp() {
+            let factions = vec![faction(Race::Blessed), faction(Race::Raceless)];
+            let mut state = GameState::new(factions, map::open_map(MapId::Debug));
+            state.scoring_tiles = scoringtile::all_scoring_tiles()
+                .into_iter()
+                .filter(|tile| tile.left_side.goal == LeftSideGoal::BuildWorkshop)
+                .take(1)
+                .collect();
+            let event = ScoringEvent::Built {
+                building: Building::Workshop,
+            };
+
+            state.score_event(0, event);
+            state.score_event(1, event);
+
+            assert_eq!(state.awards()[0].vp, VP(3));
+            assert_eq!(state.awards()[1].vp, VP(2));
+            assert_eq!(state.factions()[0].vp(), VP(23));
+        }
+    }
+
+    mod monks {
+        use super::*;
+
+        #[test]
+        fn schools_are_cheaper() {
+            let monks = faction(Race::Monks).upgrade_cost(Building::School, false);
+            let raceless = faction(Race::Raceless).upgrade_cost(Building::School, false);
+
+            assert_eq!(
+                monks.unwrap() + &Resources::from(Coins(2)),
+                raceless.unwrap()
+            );
+            assert_eq!(
+                faction(Race::Monks).upgrade_cost(Building::Guild, false),
+                faction(Race::Raceless).upgrade_cost(Building::Guild, false)
+            );
+        }
+    }
+
+    mod felines {
+        use super::*;
+
+        use crate::leech::{LeechOffer, LeechQueue};
+
+        #[test]
+        fn accepting_power_costs_one_vp_less() {
+            let mut factions = vec![faction(Race::Raceless), faction(Race::Felines)];
+            let mut queue = LeechQueue::new();
+            queue.push(vec![LeechOffer {
+                player_id: 1,
+                builder: 0,
+                power: 3,
+            }]);
+
+            queue.decide(1, true, &mut factions).unwrap();
+
+            assert_eq!(factions[1].vp(), VP(19));
+            assert_eq!(factions[1].power().amount(2), 10);
+        }
+    }
+
+    mod navigators {
+        use super::*;
+
+        use crate::reachability;
+
+        #[test]
+        fn sail_without_sailing_level() {
+            let map = map::open_map(MapId::Debug);
+            let buildings = [(3, 2)]; // Next to the water of row 4
+
+            let navigators =
+                reachability::reachable_by(&map, &buildings, &faction(Race::Navigators));
+            let raceless = reachability::reachable_by(&map, &buildings, &faction(Race::Raceless));
+
+            assert!(navigators.route_to((5, 2)).is_some());
+            assert!(!raceless.contains((5, 2)));
+        }
+    }
+
+    mod goblins {
+        use super::*;
+
+        use crate::terraform;
+
+        #[test]
+        fn spades_give_coins() {
+            let mut map = map::open_map(MapId::Debug);
+            let mut goblins = Faction::new(&Race::Goblins, &Color::Brown);
+
+            // Yellow is one spade from brown
+            terraform::terraform(&mut map, (1, 0), &mut goblins, Color::Brown).unwrap();
+            terraform::dig(&mut map, (1, 1), &mut goblins, 1).unwrap();
+
+            let json = serde_json::to_value(&goblins).unwrap();
+            assert_eq!(json["coins"], 19);
+        }
+    }
+
+    mod omar {
+        use super::*;
+
+        #[test]
+        fn guilds_are_always_discounted() {
+            let omar = faction(Race::Omar);
+
+            assert_eq!(
+                omar.upgrade_cost(Building::Guild, false),
+                faction(Race::Raceless).upgrade_cost(Building::Guild, true)
+            );
+            assert_eq!(
+                omar.upgrade_cost(Building::Guild, true),
+                omar.upgrade_cost(Building::Guild, false)
+            );
+        }
+    }
+
+    mod illusionists {
+        use super::*;
+
+        use crate::leech::{LeechOffer, LeechQueue};
+
+        #[test]
+        fn accepted_offers_give_extra_power() {
+            let mut factions = vec![faction(Race::Raceless), faction(Race::Illusionists)];
+            let mut queue = LeechQueue::new();
+            queue.push(vec![LeechOffer {
+                player_id: 1,
+                builder: 0,
+                power: 2,
+            }]);
+
+            queue.decide(1, true, &mut factions).unwrap();
+
+            // Illusionists start with two medicine steps but the usual power
+            assert_eq!(factions[1].power().amount(1), 2);
+            assert_eq!(factions[1].vp(), VP(19));
+        }
+    }
+
+    mod inventors {
+        use super::*;
+
+        #[test]
+        fn extra_scholar_income() {
+            let income = faction(Race::Inventors).total_income();
+
+            assert_eq!(
+                income,
+                faction(Race::Raceless).total_income() + &Resources::from(Scholars(1))
+            );
+        }
+    }
+
+    mod philosophers {
+        use super::*;
+
+        use crate::gamephase::finalscoring::FinalScoring;
+        use crate::gamephase::GameState;
+
+        #[test]
+        fn leftover_scholars_are_worth_a_vp_each() {
+            let mut factions = vec![faction(Race::Philosophers), faction(Race::Raceless)];
+            for faction in &mut factions {
+                faction.gain(&Resources::from(Scholars(4)));
+            }
+            let state = GameState::new(factions, map::open_map(MapId::Debug));
+
+            let scoring = FinalScoring::new(state);
+
+            // 15 coins and 3 tools, plus the scholars
+            assert_eq!(scoring.scores()[0].resources, VP(6 + 4));
+            assert_eq!(scoring.scores()[1].resources, VP(22 / 3));
+        }
+    }
+
+    mod lizards {
+        use super::*;
+
+        use crate::terraform;
+
+        #[test]
+        fn digging_is_cheaper() {
+            let lizards = Faction::new(&Race::Lizards, &Color::Yellow);
+
+            assert_eq!(
+                terraform::terraform_cost(&lizards, Color::Black, Color::Yellow),
+                crate::resources::Tools(4)
+            );
+        }
+    }
+
+    mod psychics {
+        use super::*;
+
+        #[test]
+        fn extra_power_income() {
+            let income = faction(Race::Psychics).total_income();
+
+            assert_eq!(
+                income,
+                faction(Race::Raceless).total_income() + &Resources::from(Power(2))
+            );
+        }
+    }
+
+    mod moles {
+        use super::*;
+
+        use crate::reachability;
+
+        #[test]
+        fn tunnel_two_steps_away() {
+            let map = map::open_map(MapId::Debug);
+            let buildings = [(1, 2)];
+
+            let moles = reachability::reachable_by(&map, &buildings, &faction(Race::Moles));
+            let raceless = reachability::reachable_by(&map, &buildings, &faction(Race::Raceless));
+
+            assert!(moles.direct.contains(&(1, 4)));
+            assert!(moles.direct.contains(&(3, 2)));
+            assert!(!raceless.contains((1, 4)));
+            assert!(raceless.direct.iter().all(|pos| moles.direct.contains(pos)));
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::faction::Faction;
+use crate::map::{self, Map, Pos, Terrain};
+
+/// A land hex that can only be reached by sailing across water.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShippingRoute {
+    pub destination: Pos,
+    pub path: Vec<Pos>, // Water hexes in the order they are sailed through
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reachability {
+    pub direct: Vec<Pos>,             // Land hexes adjacent to one of the buildings
+    pub shipping: Vec<ShippingRoute>, // Land hexes only reachable across water
+}
+
+impl Reachability {
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.direct.contains(&pos) || self.route_to(pos).is_some()
+    }
+
+    pub fn route_to(&self, pos: Pos) -> Option<&ShippingRoute> {
+        self.shipping.iter().find(|r| r.destination == pos)
+    }
+}
+
+/// Finds all land hexes the faction can reach from its buildings, see `reachable_hexes`.
+/// Some races sail further or tunnel to land hexes two steps away.
+pub fn reachable_by(map: &Map, buildings: &[Pos], faction: &Faction) -> Reachability {
+    let race = faction.race();
+    let mut reach = reachable_hexes(
+        map,
+        buildings,
+        faction.sailing_level() + race.sailing_bonus(),
+    );
+
+    if race.tunnels() {
+        let mut tunneled: Vec<Pos> = buildings
+            .iter()
+            .flat_map(|&b| map::ring(b, 2))
+            .filter(|&p| {
+                matches!(map[p.0][p.1].terrain, Terrain::Land(_))
+                    && !buildings.contains(&p)
+                    && !reach.direct.contains(&p)
+            })
+            .collect();
+        tunneled.sort();
+        tunneled.dedup();
+
+        reach
+            .shipping
+            .retain(|r| !tunneled.contains(&r.destination));
+        reach.direct.extend(tunneled);
+        reach.direct.sort();
+    }
+
+    reach
+}
+
+/// Finds all land hexes that a player with buildings on `buildings` can reach.
+///
+/// Hexes next to a building are directly reachable. Other land hexes are reachable by
+/// shipping if they border a body of connected water that can be crossed in at most
+/// `sailing_level` water hexes, starting from water next to one of the buildings.
+pub fn reachable_hexes(map: &Map, buildings: &[Pos], sailing_level: u32) -> Reachability {
+    let is_land = |p: Pos| matches!(map[p.0][p.1].terrain, Terrain::Land(_));
+    let is_water = |p: Pos| map[p.0][p.1].terrain == Terrain::Water;
+
+    let mut direct: Vec<Pos> = buildings
+        .iter()
+        .flat_map(|&b| map::neighbors(b))
+        .filter(|&p| is_land(p) && !buildings.contains(&p))
+        .collect();
+    direct.sort();
+    direct.dedup();
+
+    // Breadth-first search across water, remembering where each water hex was entered from.
+    let mut came_from: HashMap<Pos, Option<Pos>> = HashMap::new();
+    let mut queue: VecDeque<(Pos, u32)> = VecDeque::new();
+
+    let mut start: Vec<Pos> = buildings
+        .iter()
+        .flat_map(|&b| map::neighbors(b))
+        .filter(|&p| is_water(p))
+        .collect();
+    start.sort();
+    start.dedup();
+
+    if sailing_level > 0 {
+        for pos in start {
+            came_from.insert(pos, None);
+            queue.push_back((pos, 1));
+        }
+    }
+
+    let mut shipping: Vec<ShippingRoute> = Vec::new();
+    while let Some((water, depth)) = queue.pop_front() {
+        for next in map::neighbors(water) {
+            if is_water(next) {
+                if depth < sailing_level && !came_from.contains_key(&next) {
+                    came_from.insert(next, Some(water));
+                    queue.push_back((next, depth + 1));
+                }
+            } else if !buildings.contains(&next)
+                && !direct.contains(&next)
+                && !shipping.iter().any(|r| r.destination == next)
+            {
+                shipping.push(ShippingRoute {
+                    destination: next,
+                    path: backtrack(&came_from, water),
+                });
+            }
+        }
+    }
+    shipping.sort_by_key(|r| r.destination);
+
+    Reachability { direct, shipping }
+}
+
+fn backtrack(came_from: &HashMap<Pos, Option<Pos>>, last: Pos) -> Vec<Pos> {
+    let mut path = vec![last];
+    while let Some(Some(prev)) = came_from.get(path.last().unwrap()) {
+        path.push(*prev);
+    }
+    path.reverse();
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{open_map, MapId};
+
+    #[test]
+    fn neighbors_are_directly_reachable() {
+        let map = open_map(MapId::Debug);
+
+        let reach = reachable_hexes(&map, &[(1, 3)], 0);
+
+        // Row 0 is only water on the debug map
+        assert_eq!(reach.direct, vec![(1, 2), (1, 4), (2, 3), (2, 4)]);
+        assert!(reach.shipping.is_empty());
+    }
+
+    #[test]
+    fn water_is_never_reachable() {
+        let map = open_map(MapId::Debug);
+
+        let reach = reachable_hexes(&map, &[(3, 2)], 3);
+
+        assert!(!reach.contains((4, 2)));
+        assert!(reach
+            .shipping
+            .iter()
+            .all(|r| map[r.destination.0][r.destination.1].terrain != Terrain::Water));
+    }
+
+    #[test]
+    fn cross_one_water_hex_with_sailing_level_one() {
+        let map = open_map(MapId::Debug);
+
+        // Row 4 is water on the debug map, so row 5 can be reached from row 3 by sailing.
+        let reach = reachable_hexes(&map, &[(3, 2)], 1);
+
+        let route = reach.route_to((5, 2)).unwrap();
+        assert_eq!(route.path.len(), 1);
+        assert!(map::is_neighbor(route.path[0], (3, 2)));
+        assert!(map::is_neighbor(route.path[0], (5, 2)));
+    }
+
+    #[test]
+    fn no_shipping_without_sailing_level() {
+        let map = open_map(MapId::Debug);
+
+        let reach = reachable_hexes(&map, &[(3, 2)], 0);
+
+        assert!(reach.shipping.is_empty());
+        assert!(!reach.contains((5, 2)));
+    }
+
+    #[test]
+    fn sailing_level_limits_range() {
+        let map = open_map(MapId::Debug);
+        let building = (3, 0);
+        let far_away = (5, 3);
+
+        assert!(!reachable_hexes(&map, &[building], 1).contains(far_away));
+
+        let reach = reachable_hexes(&map, &[building], 3);
+        let route = reach.route_to(far_away).unwrap();
+        assert_eq!(route.path.len(), 3);
+    }
+
+    #[test]
+    fn shipping_path_is_connected_water() {
+        let map = open_map(MapId::Base);
+
+        let reach = reachable_hexes(&map, &[(0, 0), (4, 3)], 3);
+
+        assert!(!reach.shipping.is_empty());
+        for route in &reach.shipping {
+            assert!(route.path.len() <= 3);
+            assert!(route
+                .path
+                .iter()
+                .all(|p| map[p.0][p.1].terrain == Terrain::Water));
+            for (a, b) in route.path.iter().zip(route.path.iter().skip(1)) {
+                assert!(map::is_neighbor(*a, *b));
+            }
+            assert!(map::is_neighbor(
+                *route.path.last().unwrap(),
+                route.destination
+            ));
+        }
+    }
+
+    #[test]
+    fn directly_reachable_hexes_are_not_listed_as_shipping() {
+        let map = open_map(MapId::Base);
+
+        let reach = reachable_hexes(&map, &[(2, 4)], 2);
+
+        for route in &reach.shipping {
+            assert!(!reach.direct.contains(&route.destination));
+        }
+    }
+}
@@ -1,6 +1,12 @@
-use std::{iter, ops};
+use std::ops;
 
-use serde::{ser::SerializeStruct, Serialize, Serializer};
+use enum_iterator::all;
+use serde::{
+    ser::{SerializeMap, SerializeStruct},
+    Deserialize, Serialize, Serializer,
+};
+
+use crate::common::Discipline;
 
 pub trait Resource:
     From<u32> + Copy + Clone + ops::Add<Output = Self> + ops::Sub<Output = Self>
@@ -10,19 +16,13 @@ pub trait Resource:
     fn get_val(&self) -> u32;
 }
 
-macro_rules! define_resource {
-    ($name:ident, $idx:expr) => {
-        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+macro_rules! define_amount {
+    ($name:ident) => {
+        #[derive(
+            Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+        )]
         pub struct $name(pub u32);
 
-        impl Resource for $name {
-            const IDX: usize = $idx;
-
-            fn get_val(&self) -> u32 {
-                self.0
-            }
-        }
-
         impl From<u32> for $name {
             fn from(val: u32) -> Self {
                 Self(val)
@@ -59,25 +59,53 @@ macro_rules! define_resource {
     };
 }
 
+macro_rules! define_resource {
+    ($name:ident, $idx:expr) => {
+        define_amount!($name);
+
+        impl Resource for $name {
+            const IDX: usize = $idx;
+
+            fn get_val(&self) -> u32 {
+                self.0
+            }
+        }
+    };
+}
+
 define_resource!(Tools, 0);
 define_resource!(Coins, 1);
 define_resource!(Scholars, 2);
-define_resource!(Books, 3); // TODO: Books have colors
-define_resource!(Power, 4);
-const NUM_RESOURCES: usize = 5;
+define_resource!(Power, 3);
+const NUM_RESOURCES: usize = 4;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+// Books always have the color of a discipline and are therefore not a Resource of their own.
+// A plain amount of books is one of any colors.
+define_amount!(Books);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Resources {
-    amounts: Vec<u32>, // Resources are stored in their respective Resource::IDX.
+    amounts: [u32; NUM_RESOURCES], // Resources are stored in their respective Resource::IDX.
+    books: [u32; 4],               // Indexed by Discipline
 }
 
 impl Resources {
     pub fn none() -> Self {
         Self {
-            amounts: iter::repeat(0).take(NUM_RESOURCES).collect(),
+            amounts: [0; NUM_RESOURCES],
+            books: [0; 4],
         }
     }
 
+    pub fn books(&self, disc: Discipline) -> Books {
+        Books(self.books[disc as usize])
+    }
+
+    /// Books of all colors together.
+    pub fn total_books(&self) -> Books {
+        Books(self.books.iter().sum())
+    }
+
     pub fn get<T: Resource>(&self) -> T {
         T::from(self.amounts[T::IDX])
     }
@@ -104,16 +132,40 @@ impl<T: Resource> From<T> for Resources {
     }
 }
 
+impl From<(Discipline, Books)> for Resources {
+    fn from((disc, books): (Discipline, Books)) -> Self {
+        let mut res = Resources::none();
+        res.books[disc as usize] = books.0;
+
+        res
+    }
+}
+
+// Books indexed by Discipline
+impl From<[Books; 4]> for Resources {
+    fn from(books: [Books; 4]) -> Self {
+        let mut res = Resources::none();
+        res.books = books.map(|b| b.0);
+
+        res
+    }
+}
+
 impl ops::Add<&Resources> for Resources {
     type Output = Self;
 
     fn add(self, rhs: &Self) -> Self::Output {
         // Pointwise addition.
-        let amounts: Vec<u32> = std::iter::zip(self.amounts.iter(), rhs.amounts.iter())
-            .map(|(a, b)| a + b)
-            .collect();
+        let mut amounts = self.amounts;
+        for (a, b) in amounts.iter_mut().zip(rhs.amounts) {
+            *a += b;
+        }
+        let mut books = self.books;
+        for (a, b) in books.iter_mut().zip(rhs.books) {
+            *a += b;
+        }
 
-        Self { amounts }
+        Self { amounts, books }
     }
 }
 
@@ -122,11 +174,16 @@ impl ops::Sub<&Resources> for Resources {
 
     fn sub(self, rhs: &Self) -> Self::Output {
         // Pointwise subtraction.
-        let amounts: Vec<u32> = std::iter::zip(self.amounts.iter(), rhs.amounts.iter())
-            .map(|(a, b)| a - b)
-            .collect();
+        let mut amounts = self.amounts;
+        for (a, b) in amounts.iter_mut().zip(rhs.amounts) {
+            *a -= b;
+        }
+        let mut books = self.books;
+        for (a, b) in books.iter_mut().zip(rhs.books) {
+            *a -= b;
+        }
 
-        Self { amounts }
+        Self { amounts, books }
     }
 }
 
@@ -136,7 +193,8 @@ impl Serialize for Resources {
         S: Serializer,
     {
         // Only serialize non-zero elements
-        let num_non_zero = self.amounts.iter().filter(|&x| *x != 0).count();
+        let num_non_zero = self.amounts.iter().filter(|&x| *x != 0).count()
+            + usize::from(self.total_books().0 != 0);
 
         let mut seq = serializer.serialize_struct("resources", num_non_zero)?;
         if self.amounts[Tools::IDX] != 0 {
@@ -148,8 +206,8 @@ impl Serialize for Resources {
         if self.amounts[Scholars::IDX] != 0 {
             seq.serialize_field("scholars", &self.amounts[Scholars::IDX])?;
         }
-        if self.amounts[Books::IDX] != 0 {
-            seq.serialize_field("books", &self.amounts[Books::IDX])?;
+        if self.total_books().0 != 0 {
+            seq.serialize_field("books", &BookColors(&self.books, false))?;
         }
         if self.amounts[Power::IDX] != 0 {
             seq.serialize_field("power", &self.amounts[Power::IDX])?;
@@ -158,3 +216,35 @@ impl Serialize for Resources {
         seq.end()
     }
 }
+
+/// Serializes books indexed by Discipline with one field for each color.
+pub(crate) fn serialize_books<S>(
+    books: &[Books; 4],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    BookColors(&books.map(|b| b.0), true).serialize(serializer)
+}
+
+// Books by color. Colors without books are skipped unless all of them are asked for.
+struct BookColors<'a>(&'a [u32; 4], bool);
+
+impl Serialize for BookColors<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let BookColors(books, all_colors) = *self;
+
+        let colors = all::<Discipline>()
+            .filter(|&disc| all_colors || books[disc as usize] != 0)
+            .collect::<Vec<_>>();
+        let mut map = serializer.serialize_map(Some(colors.len()))?;
+        for disc in colors {
+            map.serialize_entry(&disc, &books[disc as usize])?;
+        }
+        map.end()
+    }
+}
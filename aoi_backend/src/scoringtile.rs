@@ -1,25 +1,30 @@
+use crate::building::Building;
 use crate::common::{Discipline, VP};
 
 use itertools::Itertools;
 use rand;
 use rand::prelude::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
 use std::hash::{Hash, Hasher};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize)]
 pub struct ScoringTile {
     pub left_side: LeftSide,
     pub right_side: RightSide,
     id: u32,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone, Copy, PartialEq, Serialize)]
 pub struct LeftSide {
     pub goal: LeftSideGoal,
     pub amount: VP,
+    #[serde(skip)]
     _private: (), // This will hinder other modules to create new instances of this struct.
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
 pub enum LeftSideGoal {
     BuildWorkshop,
     BuildGuild,
@@ -27,21 +32,64 @@ pub enum LeftSideGoal {
     BuildBigBuilding,
     Dig,
     AdvanceDiscipline,
-    FoundCity,
     AdvanceSailingOrDigging,
-    GainInnovationTile,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// Something a player did during a round that the left side of a scoring tile may
+/// award VP for.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum ScoringEvent {
+    Built { building: Building },
+    Dug { spades: u32 },
+    AdvancedDiscipline { disc: Discipline, steps: u32 },
+    AdvancedSailing,
+    AdvancedDigging,
+}
+
+impl LeftSide {
+    /// VP awarded for the event, zero unless it meets the goal.
+    pub fn vp_for(&self, event: &ScoringEvent) -> VP {
+        let times = match (self.goal, event) {
+            (LeftSideGoal::BuildWorkshop, ScoringEvent::Built { building }) => {
+                (*building == Building::Workshop) as u32
+            }
+            (LeftSideGoal::BuildGuild, ScoringEvent::Built { building }) => {
+                (*building == Building::Guild) as u32
+            }
+            (LeftSideGoal::BuildSchool, ScoringEvent::Built { building }) => {
+                (*building == Building::School) as u32
+            }
+            (LeftSideGoal::BuildBigBuilding, ScoringEvent::Built { building }) => {
+                matches!(building, Building::University | Building::Palace) as u32
+            }
+            (LeftSideGoal::Dig, ScoringEvent::Dug { spades }) => *spades,
+            (LeftSideGoal::AdvanceDiscipline, ScoringEvent::AdvancedDiscipline { steps, .. }) => {
+                *steps
+            }
+            (
+                LeftSideGoal::AdvanceSailingOrDigging,
+                ScoringEvent::AdvancedSailing | ScoringEvent::AdvancedDigging,
+            ) => 1,
+            _ => 0,
+        };
+
+        VP(self.amount.0 * times)
+    }
+}
+
+#[allow(clippy::manual_non_exhaustive)]
+#[derive(Clone, Copy, PartialEq, Serialize)]
 pub struct RightSide {
     pub rew: RightSideReward,
     pub rew_amount: u32,
     pub disc: Discipline,
     pub disc_requirement: u32,
+    #[serde(skip)]
     _private: (), // This will hinder other modules to create new instances of this struct.
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Serialize)]
 pub enum RightSideReward {
     Books,
     Coins,
@@ -64,10 +112,13 @@ impl PartialEq for ScoringTile {
 impl Eq for ScoringTile {}
 
 pub fn new_game_random_tiles() -> Vec<ScoringTile> {
-    let mut rng = rand::thread_rng();
+    new_game_tiles_from_rng(&mut rand::thread_rng())
+}
 
+/// Picks the scoring tiles of a game using the given random number generator.
+pub fn new_game_tiles_from_rng<R: Rng + ?Sized>(rng: &mut R) -> Vec<ScoringTile> {
     let mut tile_pool = all_scoring_tiles();
-    tile_pool.shuffle(&mut rng);
+    tile_pool.shuffle(rng);
 
     let mut r56_scoring_tiles = tile_pool
         .iter()
@@ -78,7 +129,7 @@ pub fn new_game_random_tiles() -> Vec<ScoringTile> {
 
     let r14_scoring_tiles = tile_pool
         .iter()
-        .filter(|tile| !r56_scoring_tiles.contains(&tile))
+        .filter(|tile| !r56_scoring_tiles.contains(tile))
         .take(4)
         .copied()
         .collect_vec();
@@ -89,7 +140,7 @@ pub fn new_game_random_tiles() -> Vec<ScoringTile> {
     res
 }
 
-fn all_scoring_tiles() -> Vec<ScoringTile> {
+pub(crate) fn all_scoring_tiles() -> Vec<ScoringTile> {
     let mut id_gen = 0..;
     vec![
         ScoringTile {
@@ -227,21 +278,6 @@ fn all_scoring_tiles() -> Vec<ScoringTile> {
             },
             id: id_gen.next().unwrap(),
         },
-        ScoringTile {
-            left_side: LeftSide {
-                goal: LeftSideGoal::FoundCity,
-                amount: VP(5),
-                _private: (),
-            },
-            right_side: RightSide {
-                rew: RightSideReward::Spades,
-                rew_amount: 1,
-                disc: Discipline::Engineering,
-                disc_requirement: 4,
-                _private: (),
-            },
-            id: id_gen.next().unwrap(),
-        },
         ScoringTile {
             left_side: LeftSide {
                 goal: LeftSideGoal::AdvanceSailingOrDigging,
@@ -257,21 +293,6 @@ fn all_scoring_tiles() -> Vec<ScoringTile> {
             },
             id: id_gen.next().unwrap(),
         },
-        ScoringTile {
-            left_side: LeftSide {
-                goal: LeftSideGoal::GainInnovationTile,
-                amount: VP(5),
-                _private: (),
-            },
-            right_side: RightSide {
-                rew: RightSideReward::Power,
-                rew_amount: 3,
-                disc: Discipline::Law,
-                disc_requirement: 2,
-                _private: (),
-            },
-            id: id_gen.next().unwrap(),
-        },
     ]
 }
 
@@ -280,6 +301,9 @@ mod tests {
     use crate::helpers::contains_duplicates;
 
     use super::*;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
     #[test]
     fn scoring_tiles_for_new_game_are_6() {
         for _ in 0..100 {
@@ -296,4 +320,89 @@ mod tests {
             assert!(!contains_duplicates(&new_game_random_tiles()));
         }
     }
+
+    #[test]
+    fn same_seed_gives_same_scoring_tiles() {
+        let a = new_game_tiles_from_rng(&mut StdRng::seed_from_u64(7));
+        let b = new_game_tiles_from_rng(&mut StdRng::seed_from_u64(7));
+
+        assert!(a == b);
+    }
+
+    fn left_side(goal: LeftSideGoal) -> LeftSide {
+        all_scoring_tiles()
+            .into_iter()
+            .find(|tile| tile.left_side.goal == goal)
+            .unwrap()
+            .left_side
+    }
+
+    #[test]
+    fn only_events_meeting_the_goal_award_vp() {
+        let workshop = ScoringEvent::Built {
+            building: Building::Workshop,
+        };
+        let palace = ScoringEvent::Built {
+            building: Building::Palace,
+        };
+
+        assert_eq!(
+            left_side(LeftSideGoal::BuildWorkshop).vp_for(&workshop),
+            VP(2)
+        );
+        assert_eq!(
+            left_side(LeftSideGoal::BuildWorkshop).vp_for(&palace),
+            VP(0)
+        );
+        assert_eq!(
+            left_side(LeftSideGoal::BuildBigBuilding).vp_for(&palace),
+            VP(5)
+        );
+        assert_eq!(left_side(LeftSideGoal::Dig).vp_for(&workshop), VP(0));
+        assert_eq!(
+            left_side(LeftSideGoal::AdvanceSailingOrDigging).vp_for(&ScoringEvent::AdvancedDigging),
+            VP(3)
+        );
+    }
+
+    #[test]
+    fn every_goal_can_be_scored() {
+        let events = enum_iterator::all::<Building>()
+            .map(|building| ScoringEvent::Built { building })
+            .chain([
+                ScoringEvent::Dug { spades: 1 },
+                ScoringEvent::AdvancedDiscipline {
+                    disc: Discipline::Law,
+                    steps: 1,
+                },
+                ScoringEvent::AdvancedSailing,
+                ScoringEvent::AdvancedDigging,
+            ])
+            .collect_vec();
+
+        for tile in all_scoring_tiles() {
+            assert!(
+                events
+                    .iter()
+                    .any(|event| tile.left_side.vp_for(event) > VP(0)),
+                "{:?} can never be scored",
+                tile.left_side.goal
+            );
+        }
+    }
+
+    #[test]
+    fn vp_is_awarded_per_spade_and_discipline_step() {
+        let dug = ScoringEvent::Dug { spades: 3 };
+        let advanced = ScoringEvent::AdvancedDiscipline {
+            disc: Discipline::Law,
+            steps: 2,
+        };
+
+        assert_eq!(left_side(LeftSideGoal::Dig).vp_for(&dug), VP(6));
+        assert_eq!(
+            left_side(LeftSideGoal::AdvanceDiscipline).vp_for(&advanced),
+            VP(2)
+        );
+    }
 }
@@ -0,0 +1,233 @@
+use std::cmp::min;
+
+use crate::common::Color;
+use crate::error::AoiError;
+use crate::faction::Faction;
+use crate::map::{self, Map, Pos, Terrain};
+use crate::resources::{Coins, Resources, Tools};
+use crate::Result;
+
+// The terrain colors in the order they appear on the (cyclic) color wheel.
+const COLOR_WHEEL: [Color; 7] = [
+    Color::Yellow,
+    Color::Brown,
+    Color::Black,
+    Color::Blue,
+    Color::Green,
+    Color::Gray,
+    Color::Red,
+];
+
+/// Number of spades needed to turn terrain of color `from` into color `to`.
+pub fn spade_distance(from: Color, to: Color) -> u32 {
+    let (a, b) = match (wheel_index(from), wheel_index(to)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return 0, // Colorless is only used for testing and can be built on anywhere
+    };
+
+    let diff = a.abs_diff(b);
+    diff.min(COLOR_WHEEL.len() - diff) as u32
+}
+
+/// Tools needed for a faction to terraform a hex of color `from` into color `to`.
+pub fn terraform_cost(faction: &Faction, from: Color, to: Color) -> Tools {
+    Tools(spade_distance(from, to) * faction.digging_cost().0)
+}
+
+/// Terraforms the land hex at `pos` into color `to` and charges the faction the tools
+/// required for the digging. Returns the number of spades used.
+pub fn terraform(map: &mut Map, pos: Pos, faction: &mut Faction, to: Color) -> Result<u32> {
+    let from = terrain_color(map, pos)?;
+    if to == Color::Colorless {
+        return Err(AoiError::InvalidTerraformColor { color: to });
+    }
+    if from == to {
+        return Err(AoiError::AlreadyColor { pos, color: to });
+    }
+
+    let spades = spade_distance(from, to);
+    faction.pay(&Resources::from(terraform_cost(faction, from, to)))?;
+    map[pos.0][pos.1].terrain = Terrain::Land(to);
+    reward_spades(faction, spades);
+
+    Ok(spades)
+}
+
+/// Digs the land hex at `pos` up to `spades` steps closer to the faction's home color,
+/// free of charge. Returns the number of spades used.
+pub fn dig(map: &mut Map, pos: Pos, faction: &mut Faction, spades: u32) -> Result<u32> {
+    let from = terrain_color(map, pos)?;
+    let to = faction.get_color();
+    let (Some(mut idx), Some(to_idx)) = (wheel_index(from), wheel_index(to)) else {
+        return Err(AoiError::InvalidTerraformColor { color: to });
+    };
+    if from == to {
+        return Err(AoiError::AlreadyColor { pos, color: to });
+    }
+
+    // Each spade turns the hex one step around the wheel, the shorter way
+    let len = COLOR_WHEEL.len();
+    let forward = (to_idx + len - idx) % len <= len / 2;
+    let used = min(spades, spade_distance(from, to));
+    for _ in 0..used {
+        idx = if forward {
+            (idx + 1) % len
+        } else {
+            (idx + len - 1) % len
+        };
+    }
+    map[pos.0][pos.1].terrain = Terrain::Land(COLOR_WHEEL[idx]);
+    reward_spades(faction, used);
+
+    Ok(used)
+}
+
+// Races may gain something for every spade used
+fn reward_spades(faction: &mut Faction, spades: u32) {
+    let coins = faction.race().coins_per_spade();
+    faction.gain(&Resources::from(Coins(coins.0 * spades)));
+}
+
+// Color of a hex that may be terraformed, i.e. land without any building.
+fn terrain_color(map: &Map, pos: Pos) -> Result<Color> {
+    if !map::inside_bounds(pos) {
+        return Err(AoiError::OutsideMap { pos });
+    }
+
+    let hex = &map[pos.0][pos.1];
+    if hex.building.is_some() {
+        return Err(AoiError::HexOccupied { pos });
+    }
+
+    match hex.terrain {
+        Terrain::Land(color) => Ok(color),
+        Terrain::Water => Err(AoiError::WaterHex { pos }),
+    }
+}
+
+fn wheel_index(color: Color) -> Option<usize> {
+    COLOR_WHEEL.iter().position(|&c| c == color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::building::Building;
+    use crate::map::{open_map, MapId};
+    use crate::race::Race;
+
+    use parameterized::parameterized;
+
+    #[test]
+    fn spade_distance_is_symmetric_and_cyclic() {
+        assert_eq!(spade_distance(Color::Yellow, Color::Yellow), 0);
+        assert_eq!(spade_distance(Color::Yellow, Color::Brown), 1);
+        assert_eq!(spade_distance(Color::Brown, Color::Yellow), 1);
+        assert_eq!(spade_distance(Color::Yellow, Color::Black), 2);
+        assert_eq!(spade_distance(Color::Yellow, Color::Blue), 3);
+        assert_eq!(spade_distance(Color::Yellow, Color::Green), 3);
+        assert_eq!(spade_distance(Color::Yellow, Color::Gray), 2);
+        assert_eq!(spade_distance(Color::Yellow, Color::Red), 1);
+        assert_eq!(spade_distance(Color::Red, Color::Brown), 2);
+    }
+
+    #[test]
+    fn spade_distance_never_exceeds_three() {
+        for a in COLOR_WHEEL {
+            for b in COLOR_WHEEL {
+                assert!(spade_distance(a, b) <= 3);
+            }
+        }
+    }
+
+    // Starting tools are enough for exactly one spade
+    #[parameterized(to = { Color::Brown, Color::Red })]
+    fn terraform_changes_terrain_and_charges_tools(to: Color) {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        let pos = (1, 0); // Yellow
+        let tools_before = faction.tools();
+
+        let spades = terraform(&mut map, pos, &mut faction, to).unwrap();
+
+        assert_eq!(spades, spade_distance(Color::Yellow, to));
+        assert_eq!(map[pos.0][pos.1].terrain, Terrain::Land(to));
+        assert_eq!(
+            faction.tools(),
+            Tools(tools_before.0 - spades * faction.digging_cost().0)
+        );
+    }
+
+    #[test]
+    fn terraform_without_enough_tools_changes_nothing() {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        let pos = (1, 0); // Yellow
+
+        // Two spades costs more tools than a faction starts with
+        assert!(terraform(&mut map, pos, &mut faction, Color::Black).is_err());
+
+        assert_eq!(map[pos.0][pos.1].terrain, Terrain::Land(Color::Yellow));
+        assert_eq!(faction.tools(), Tools(3));
+    }
+
+    #[parameterized(spades = { 1, 2, 3 })]
+    fn dig_steps_toward_color_for_free(spades: u32) {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Gray);
+        let pos = (1, 0); // Yellow, two steps from black and gray
+
+        let used = dig(&mut map, pos, &mut faction, spades).unwrap();
+
+        let expected = [Color::Red, Color::Gray, Color::Gray][spades as usize - 1];
+        assert_eq!(used, min(spades, 2));
+        assert_eq!(map[pos.0][pos.1].terrain, Terrain::Land(expected));
+    }
+
+    #[test]
+    fn dig_needs_a_different_color() {
+        let mut map = open_map(MapId::Debug);
+        let mut yellow = Faction::new(&Race::Raceless, &Color::Yellow);
+        let mut colorless = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        assert!(dig(&mut map, (1, 0), &mut yellow, 1).is_err());
+        assert!(dig(&mut map, (1, 0), &mut colorless, 1).is_err());
+        assert_eq!(map[1][0].terrain, Terrain::Land(Color::Yellow));
+    }
+
+    #[test]
+    fn water_cant_be_terraformed() {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        assert!(terraform(&mut map, (4, 0), &mut faction, Color::Yellow).is_err());
+        assert_eq!(map[4][0].terrain, Terrain::Water);
+    }
+
+    #[test]
+    fn hex_with_building_cant_be_terraformed() {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+        map[1][0].building = Some((0, Building::Workshop));
+
+        assert!(terraform(&mut map, (1, 0), &mut faction, Color::Brown).is_err());
+        assert_eq!(map[1][0].terrain, Terrain::Land(Color::Yellow));
+    }
+
+    #[test]
+    fn terraform_to_same_color_is_rejected() {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        assert!(terraform(&mut map, (1, 0), &mut faction, Color::Yellow).is_err());
+    }
+
+    #[test]
+    fn terraform_outside_map_is_rejected() {
+        let mut map = open_map(MapId::Debug);
+        let mut faction = Faction::new(&Race::Raceless, &Color::Colorless);
+
+        assert!(terraform(&mut map, (map::MAP_HEIGHT, 0), &mut faction, Color::Red).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::process;
+
+use aoi_backend::bot::BotKind;
+
+mod selfplay;
+mod stats;
+
+const USAGE: &str = "\
+Plays seeded bot-vs-bot games and prints statistics over them.
+
+Usage: aoi_selfplay [OPTIONS]
+
+Options:
+  --games <N>      Number of games to play [default: 100]
+  --seed <N>       Seed of the first game, the following games count up [default: 0]
+  --bots <LIST>    Comma separated bots taking the seats, one of random, greedy and mcts.
+                   The seats rotate between games [default: random,random]
+  --format <FMT>   Output as json or csv [default: json]";
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    games: u64,
+    seed: u64,
+    bots: Vec<BotKind>,
+    format: Format,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        games: 100,
+        seed: 0,
+        bots: vec![BotKind::Random, BotKind::Random],
+        format: Format::Json,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", arg))?;
+        let number = || {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid number for {}: {}", arg, value))
+        };
+
+        match arg.as_str() {
+            "--games" => options.games = number()?,
+            "--seed" => options.seed = number()?,
+            "--bots" => options.bots = value.split(',').map(parse_bot).collect::<Result<_, _>>()?,
+            "--format" => {
+                options.format = match value.as_str() {
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => return Err(format!("Unknown format: {}", value)),
+                }
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    if !(2..=5).contains(&options.bots.len()) {
+        return Err(format!(
+            "{} bots given, 2 to 5 are needed",
+            options.bots.len()
+        ));
+    }
+
+    Ok(options)
+}
+
+fn parse_bot(name: &str) -> Result<BotKind, String> {
+    match name {
+        "random" => Ok(BotKind::Random),
+        "greedy" => Ok(BotKind::Greedy),
+        "mcts" => Ok(BotKind::Mcts),
+        _ => Err(format!("Unknown bot: {}", name)),
+    }
+}
+
+fn main() {
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(2);
+    });
+
+    let mut stats = stats::Stats::new();
+    for i in 0..options.games {
+        // Every bot gets to play from every seat
+        let mut bots = options.bots.clone();
+        let shift = i as usize % bots.len();
+        bots.rotate_left(shift);
+
+        let seed = options.seed.wrapping_add(i);
+        match selfplay::play(seed, &bots) {
+            Ok(result) => stats.record(&result),
+            Err(err) => eprintln!("Game with seed {} failed: {}", seed, err),
+        }
+    }
+
+    let report = stats.report();
+    match options.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Format::Csv => print!("{}", report.to_csv()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let options = parse_args(args(
+            "--games 10 --seed 5 --bots greedy,mcts,random --format csv",
+        ));
+
+        assert_eq!(
+            options,
+            Ok(Options {
+                games: 10,
+                seed: 5,
+                bots: vec![BotKind::Greedy, BotKind::Mcts, BotKind::Random],
+                format: Format::Csv,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        assert!(parse_args(args("--games many")).is_err());
+        assert!(parse_args(args("--bots random")).is_err());
+        assert!(parse_args(args("--bots random,smart")).is_err());
+        assert!(parse_args(args("--games")).is_err());
+        assert!(parse_args(args("--verbose 1")).is_err());
+    }
+}
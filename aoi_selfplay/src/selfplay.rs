@@ -0,0 +1,201 @@
+use std::fmt;
+
+use aoi_backend::actionlog::GameSetup;
+use aoi_backend::bonustile::BonusTile;
+use aoi_backend::bot::{self, Bot, BotKind};
+use aoi_backend::common::Color;
+use aoi_backend::error::AoiError;
+use aoi_backend::game::Action;
+use aoi_backend::gamephase::PlayerId;
+use aoi_backend::movegen;
+use aoi_backend::pregame::PreGame;
+use aoi_backend::race::Race;
+use aoi_backend::scoringtile::ScoringTile;
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::SeedableRng;
+
+/// The outcome of one bot-vs-bot game, indexed by player ID where per player.
+pub struct GameResult {
+    pub factions: Vec<(Race, Color)>,
+    pub bots: Vec<BotKind>,
+    pub vp: Vec<u32>,
+    pub num_actions: usize,
+    pub scoring_tiles: Vec<ScoringTile>,
+    pub bonus_picks: Vec<BonusTile>, // Taken with the factions and when passing
+}
+
+impl GameResult {
+    /// Share of the win of every player. Players tied for the most VP share the win.
+    pub fn wins(&self) -> Vec<f64> {
+        let best = self.vp.iter().copied().max().unwrap_or(0);
+        let num_best = self.vp.iter().filter(|&&vp| vp == best).count() as f64;
+
+        self.vp
+            .iter()
+            .map(|&vp| if vp == best { 1.0 / num_best } else { 0.0 })
+            .collect()
+    }
+}
+
+/// Why a game could not be played to the end.
+#[derive(Debug, PartialEq)]
+pub enum PlayError {
+    Game(AoiError),
+    /// The bot to act chose nothing before the game was over.
+    Unfinished {
+        player_id: PlayerId,
+    },
+}
+
+impl fmt::Display for PlayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayError::Game(err) => write!(f, "{}", err),
+            PlayError::Unfinished { player_id } => {
+                write!(
+                    f,
+                    "Bot of player {} stopped before the game ended",
+                    player_id
+                )
+            }
+        }
+    }
+}
+
+impl From<AoiError> for PlayError {
+    fn from(err: AoiError) -> Self {
+        PlayError::Game(err)
+    }
+}
+
+/// Plays a game from `seed` to the end. Factions are drawn at random from the pool and the
+/// bots take the seats in the given order.
+pub fn play(seed: u64, bots: &[BotKind]) -> Result<GameResult, PlayError> {
+    let players = bots
+        .iter()
+        .enumerate()
+        .map(|(player_id, bot)| {
+            bot.create(
+                seed.wrapping_add(player_id as u64),
+                bot::DEFAULT_MCTS_BUDGET,
+            )
+        })
+        .collect();
+
+    play_with(seed, bots, players)
+}
+
+// Plays with the given bots seated, where `bots` tells what kinds they are
+fn play_with(
+    seed: u64,
+    bots: &[BotKind],
+    mut players: Vec<Box<dyn Bot + Send>>,
+) -> Result<GameResult, PlayError> {
+    let num_players = bots.len() as u32;
+    let pool = PreGame::new_seeded(num_players, seed)
+        .faction_pool()
+        .clone();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let selected = index::sample(&mut rng, pool.len(), bots.len()).into_vec();
+
+    let setup = GameSetup {
+        seed,
+        num_players,
+        factions: selected.clone(),
+    };
+    let mut game = setup.start()?;
+
+    let mut bonus_picks = selected.iter().map(|&idx| pool[idx].1).collect::<Vec<_>>();
+    let mut num_actions = 0;
+    while let Some(player_id) = movegen::player_to_act(&game) {
+        let Some(action) = players[player_id].choose(&game) else {
+            return Err(PlayError::Unfinished { player_id });
+        };
+        game.apply(player_id, &action)?;
+        num_actions += 1;
+
+        if let Action::Pass {
+            bonus_tile: Some(tile),
+        } = action
+        {
+            bonus_picks.push(tile);
+        }
+    }
+
+    let state = game.state();
+    Ok(GameResult {
+        factions: selected
+            .iter()
+            .map(|&idx| (pool[idx].0, pool[idx].2))
+            .collect(),
+        bots: bots.to_vec(),
+        vp: state.factions().iter().map(|f| f.vp().0).collect(),
+        num_actions,
+        scoring_tiles: state.scoring_tiles().clone(),
+        bonus_picks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_bots_play_a_full_game() {
+        let bots = [BotKind::Random, BotKind::Random, BotKind::Random];
+
+        let result = play(7, &bots).unwrap();
+
+        assert_eq!(result.factions.len(), 3);
+        assert_eq!(result.vp.len(), 3);
+        assert_eq!(result.scoring_tiles.len(), 6);
+        assert!(result.num_actions > 0);
+        assert!((result.wins().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn same_seed_plays_same_game() {
+        let bots = [BotKind::Random, BotKind::Greedy];
+
+        let a = play(3, &bots).unwrap();
+        let b = play(3, &bots).unwrap();
+
+        assert_eq!(a.vp, b.vp);
+        assert_eq!(a.num_actions, b.num_actions);
+        assert_eq!(a.bonus_picks, b.bonus_picks);
+    }
+
+    #[test]
+    fn tied_players_share_the_win() {
+        let result = GameResult {
+            factions: Vec::new(),
+            bots: Vec::new(),
+            vp: vec![20, 31, 31],
+            num_actions: 0,
+            scoring_tiles: Vec::new(),
+            bonus_picks: Vec::new(),
+        };
+
+        assert_eq!(result.wins(), vec![0.0, 0.5, 0.5]);
+    }
+
+    // Gives up right away
+    struct QuittingBot;
+
+    impl Bot for QuittingBot {
+        fn choose(&mut self, _game: &aoi_backend::game::Game) -> Option<Action> {
+            None
+        }
+    }
+
+    #[test]
+    fn unfinished_game_is_an_error() {
+        let bots = [BotKind::Random, BotKind::Random];
+        let players: Vec<Box<dyn Bot + Send>> = vec![Box::new(QuittingBot), Box::new(QuittingBot)];
+
+        let result = play_with(3, &bots, players);
+
+        assert_eq!(result.err(), Some(PlayError::Unfinished { player_id: 0 }));
+    }
+}
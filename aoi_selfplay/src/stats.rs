@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::selfplay::GameResult;
+
+/// Results aggregated over many games.
+#[derive(Default)]
+pub struct Stats {
+    games: u32,
+    total_actions: u64,
+    factions: BTreeMap<String, Record>, // Keyed by race and color
+    bots: BTreeMap<String, Record>,
+    scoring_tiles: BTreeMap<String, u32>, // Number of games the tile was drawn in
+    bonus_tiles: BTreeMap<String, u32>,   // Number of times the tile was taken
+}
+
+#[derive(Default)]
+struct Record {
+    games: u32,
+    wins: f64,
+    total_vp: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub games: u32,
+    pub avg_actions: f64,
+    pub factions: Vec<PlayerRow>,
+    pub bots: Vec<PlayerRow>,
+    pub scoring_tiles: Vec<PickRow>,
+    pub bonus_tiles: Vec<PickRow>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PlayerRow {
+    pub name: String,
+    pub games: u32,
+    pub win_rate: f64,
+    pub avg_vp: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PickRow {
+    pub name: String,
+    pub count: u32,
+    pub rate: f64, // Per game for scoring tiles, and of all picks for bonus tiles
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, result: &GameResult) {
+        self.games += 1;
+        self.total_actions += result.num_actions as u64;
+
+        let players = result.factions.iter().zip(&result.bots);
+        for (((race, color), bot), (vp, win)) in players.zip(result.vp.iter().zip(result.wins())) {
+            let keys = [
+                (
+                    &mut self.factions,
+                    format!("{}/{}", name(race), name(color)),
+                ),
+                (&mut self.bots, name(bot)),
+            ];
+            for (records, key) in keys {
+                let record = records.entry(key).or_default();
+                record.games += 1;
+                record.wins += win;
+                record.total_vp += *vp as u64;
+            }
+        }
+
+        for tile in &result.scoring_tiles {
+            let key = format!(
+                "{}/{}",
+                name(&tile.left_side.goal),
+                name(&tile.right_side.rew)
+            );
+            *self.scoring_tiles.entry(key).or_default() += 1;
+        }
+        for tile in &result.bonus_picks {
+            *self.bonus_tiles.entry(name(tile)).or_default() += 1;
+        }
+    }
+
+    pub fn report(&self) -> Report {
+        let games = self.games.max(1) as f64;
+        let total_picks = self.bonus_tiles.values().sum::<u32>().max(1) as f64;
+        let player_rows = |records: &BTreeMap<String, Record>| {
+            records
+                .iter()
+                .map(|(key, record)| PlayerRow {
+                    name: key.clone(),
+                    games: record.games,
+                    win_rate: record.wins / record.games as f64,
+                    avg_vp: record.total_vp as f64 / record.games as f64,
+                })
+                .collect()
+        };
+        let pick_rows = |counts: &BTreeMap<String, u32>, total: f64| {
+            counts
+                .iter()
+                .map(|(key, &count)| PickRow {
+                    name: key.clone(),
+                    count,
+                    rate: count as f64 / total,
+                })
+                .collect()
+        };
+
+        Report {
+            games: self.games,
+            avg_actions: self.total_actions as f64 / games,
+            factions: player_rows(&self.factions),
+            bots: player_rows(&self.bots),
+            scoring_tiles: pick_rows(&self.scoring_tiles, games),
+            bonus_tiles: pick_rows(&self.bonus_tiles, total_picks),
+        }
+    }
+}
+
+impl Report {
+    /// One row per faction, bot and tile, along with the average game length. The columns
+    /// not applying to a row are left empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("category,name,count,rate,average\n");
+        writeln!(csv, "game,length,{},,{:.2}", self.games, self.avg_actions).unwrap();
+        for (category, rows) in [("faction", &self.factions), ("bot", &self.bots)] {
+            for row in rows {
+                writeln!(
+                    csv,
+                    "{},{},{},{:.4},{:.2}",
+                    category, row.name, row.games, row.win_rate, row.avg_vp
+                )
+                .unwrap();
+            }
+        }
+        for (category, rows) in [
+            ("scoring_tile", &self.scoring_tiles),
+            ("bonus_tile", &self.bonus_tiles),
+        ] {
+            for row in rows {
+                writeln!(
+                    csv,
+                    "{},{},{},{:.4},",
+                    category, row.name, row.count, row.rate
+                )
+                .unwrap();
+            }
+        }
+
+        csv
+    }
+}
+
+// The name a value serializes to, e.g. the variant of a unit enum
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoi_backend::bonustile::BonusTile;
+    use aoi_backend::bot::BotKind;
+    use aoi_backend::common::Color;
+    use aoi_backend::race::Race;
+
+    fn result(vp: Vec<u32>) -> GameResult {
+        GameResult {
+            factions: vec![(Race::Blessed, Color::Red), (Race::Monks, Color::Blue)],
+            bots: vec![BotKind::Greedy, BotKind::Random],
+            vp,
+            num_actions: 50,
+            scoring_tiles: Vec::new(),
+            bonus_picks: vec![
+                BonusTile::BonCoins,
+                BonusTile::BonCoins,
+                BonusTile::BonSpade,
+            ],
+        }
+    }
+
+    #[test]
+    fn win_rates_and_vp_are_averaged() {
+        let mut stats = Stats::new();
+
+        stats.record(&result(vec![30, 20]));
+        stats.record(&result(vec![10, 10]));
+        let report = stats.report();
+
+        assert_eq!(report.games, 2);
+        assert_eq!(report.avg_actions, 50.0);
+        assert_eq!(
+            report.factions[0],
+            PlayerRow {
+                name: String::from("Blessed/Red"),
+                games: 2,
+                win_rate: 0.75,
+                avg_vp: 20.0,
+            }
+        );
+        assert_eq!(report.bots[1].name, "Random");
+        assert_eq!(report.bots[1].win_rate, 0.25);
+    }
+
+    #[test]
+    fn bonus_tile_rate_is_share_of_picks() {
+        let mut stats = Stats::new();
+
+        stats.record(&result(vec![1, 2]));
+        let report = stats.report();
+
+        let coins = report
+            .bonus_tiles
+            .iter()
+            .find(|row| row.name == "BonCoins")
+            .unwrap();
+        assert_eq!(coins.count, 2);
+        assert!((coins.rate - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn csv_has_row_per_entry() {
+        let mut stats = Stats::new();
+        stats.record(&result(vec![3, 2]));
+
+        let csv = stats.report().to_csv();
+
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "category,name,count,rate,average");
+        assert_eq!(lines[1], "game,length,1,,50.00");
+        assert!(lines.contains(&"faction,Blessed/Red,1,1.0000,3.00"));
+        assert!(lines.contains(&"bonus_tile,BonSpade,1,0.3333,"));
+        assert_eq!(lines.len(), 2 + 2 + 2 + 2);
+    }
+}
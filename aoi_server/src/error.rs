@@ -0,0 +1,66 @@
+use aoi_backend::error::AoiError;
+use aoi_backend::gamephase::PlayerId;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::{json, Json};
+use rocket::serde::Serialize;
+
+use crate::lobby::GameId;
+
+/// Errors returned by the API. Rule violations are passed on from the backend as they
+/// are, while the other variants concern the games managed by the server.
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde", tag = "error")]
+pub enum ApiError {
+    GameNotFound {
+        game_id: GameId,
+    },
+    GameAlreadyStarted {
+        game_id: GameId,
+    },
+    GameNotStarted {
+        game_id: GameId,
+    },
+    InvalidPlayerCount {
+        num_players: u32,
+    },
+    NoSuchAction {
+        num_actions: usize,
+    },
+    InvalidToken {
+        player_id: PlayerId,
+    },
+    #[serde(skip)]
+    Game(AoiError),
+}
+
+impl ApiError {
+    fn status(&self) -> Status {
+        match self {
+            ApiError::GameNotFound { .. } | ApiError::NoSuchAction { .. } => Status::NotFound,
+            ApiError::GameAlreadyStarted { .. } | ApiError::GameNotStarted { .. } => {
+                Status::Conflict
+            }
+            ApiError::InvalidPlayerCount { .. } | ApiError::Game(_) => Status::BadRequest,
+            ApiError::InvalidToken { .. } => Status::Forbidden,
+        }
+    }
+}
+
+impl From<AoiError> for ApiError {
+    fn from(err: AoiError) -> Self {
+        ApiError::Game(err)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let body = match &self {
+            ApiError::Game(err) => json!(err),
+            _ => json!(self),
+        };
+
+        (self.status(), Json(body)).respond_to(req)
+    }
+}
@@ -0,0 +1,144 @@
+use aoi_backend::helpers::{json_obj_diff, PatchOp};
+use rocket::response::stream::Event;
+use rocket::serde::json::Value;
+use rocket::serde::Serialize;
+use rocket::tokio::sync::broadcast::{self, Receiver, Sender};
+
+// Clients lagging further behind than this are disconnected and have to resync
+const CHANNEL_CAPACITY: usize = 64;
+
+/// An update of the game state pushed to the clients. Every accepted action bumps `seq`,
+/// which lets clients detect missed updates.
+///
+/// Diffs are JSON Patches (RFC 6902) to apply to the previous state.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde", tag = "type")]
+pub enum StateUpdate {
+    Snapshot { seq: u64, state: Value },
+    Diff { seq: u64, diff: Vec<PatchOp> },
+}
+
+impl StateUpdate {
+    pub fn to_event(&self) -> Event {
+        let name = match self {
+            StateUpdate::Snapshot { .. } => "snapshot",
+            StateUpdate::Diff { .. } => "diff",
+        };
+
+        Event::json(self).event(name)
+    }
+}
+
+/// Pushes the changes of one game to its subscribed clients.
+pub struct Feed {
+    seq: u64,
+    sender: Sender<StateUpdate>,
+}
+
+impl Feed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+        Feed { seq: 0, sender }
+    }
+
+    /// Publishes the change from `old` to `new`.
+    pub fn publish(&mut self, old: &Value, new: &Value) {
+        self.seq += 1;
+        let update = match json_obj_diff(old, new) {
+            Ok(diff) => StateUpdate::Diff {
+                seq: self.seq,
+                diff,
+            },
+            // Only happens if the state isn't an object, which it always is
+            Err(_) => StateUpdate::Snapshot {
+                seq: self.seq,
+                state: new.clone(),
+            },
+        };
+
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(update);
+    }
+
+    /// Subscribes to the updates following the current `state`, which is returned as a
+    /// snapshot for the client to start from.
+    pub fn subscribe(&self, state: Value) -> (StateUpdate, Receiver<StateUpdate>) {
+        let snapshot = StateUpdate::Snapshot {
+            seq: self.seq,
+            state,
+        };
+
+        (snapshot, self.sender.subscribe())
+    }
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rocket::serde::json::json;
+
+    #[test]
+    fn subscriber_starts_from_snapshot() {
+        let feed = Feed::new();
+
+        let (snapshot, _) = feed.subscribe(json!({ "round": 0 }));
+
+        assert_eq!(
+            snapshot,
+            StateUpdate::Snapshot {
+                seq: 0,
+                state: json!({ "round": 0 })
+            }
+        );
+    }
+
+    #[test]
+    fn changes_are_pushed_as_diffs() {
+        let mut feed = Feed::new();
+        let (_, mut rx) = feed.subscribe(json!({ "round": 0, "turn": 1 }));
+
+        feed.publish(
+            &json!({ "round": 0, "turn": 1 }),
+            &json!({ "round": 0, "turn": 2 }),
+        );
+
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            StateUpdate::Diff {
+                seq: 1,
+                diff: vec![PatchOp::Replace {
+                    path: String::from("/turn"),
+                    value: json!(2)
+                }]
+            }
+        );
+    }
+
+    #[test]
+    fn diff_event_is_json_patch() {
+        let mut feed = Feed::new();
+        let (_, mut rx) = feed.subscribe(json!({ "leech": [] }));
+
+        feed.publish(
+            &json!({ "leech": [] }),
+            &json!({ "leech": [{ "power": 2 }] }),
+        );
+
+        assert_eq!(
+            json!(rx.try_recv().unwrap()),
+            json!({
+                "type": "Diff",
+                "seq": 1,
+                "diff": [{ "op": "add", "path": "/leech/0", "value": { "power": 2 } }],
+            })
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use aoi_backend::game::Action;
+use aoi_backend::gamephase::PlayerId;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::{json, Json, Value};
+use rocket::serde::Deserialize;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Route, Shutdown, State};
+
+use crate::error::ApiError;
+use crate::lobby::{GameId, Lobby};
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PlayerAction {
+    player_id: PlayerId,
+    token: String, // Handed out when joining the seat
+    action: Action,
+}
+
+/// The phase, the player to move, pending power offers and the full game state.
+#[get("/games/<game_id>/state")]
+fn get_state(lobby: &State<Lobby>, game_id: GameId) -> Result<Json<Value>, ApiError> {
+    lobby.with_table(game_id, |table| Ok(Json(table.state())))
+}
+
+/// The setup of the game and every action accepted so far.
+#[get("/games/<game_id>/log")]
+fn get_log(lobby: &State<Lobby>, game_id: GameId) -> Result<Json<Value>, ApiError> {
+    lobby.with_table(game_id, |table| Ok(Json(json!(table.game().log()))))
+}
+
+/// The state as it was after the first `num_actions` actions of the log.
+#[get("/games/<game_id>/replay/<num_actions>")]
+fn replay(
+    lobby: &State<Lobby>,
+    game_id: GameId,
+    num_actions: usize,
+) -> Result<Json<Value>, ApiError> {
+    lobby.with_table(game_id, |table| {
+        let log = table.game().log();
+        if num_actions > log.entries().len() {
+            return Err(ApiError::NoSuchAction { num_actions });
+        }
+
+        Ok(Json(json!(log.replay(num_actions)?)))
+    })
+}
+
+/// Applies the action of a player and returns the new state, which is also pushed to
+/// the subscribed clients. Bots to act next do so in the background, so their actions
+/// only reach the clients through the events.
+/// Rejected actions leave the game unchanged. Only the player holding the token of the
+/// seat may act for it.
+#[post("/games/<game_id>/actions", data = "<req>")]
+fn post_action(
+    lobby: &State<Lobby>,
+    game_id: GameId,
+    req: Json<PlayerAction>,
+) -> Result<Json<Value>, ApiError> {
+    lobby.with_seat(game_id, req.player_id, &req.token, |table| {
+        table.apply(req.player_id, req.action.clone())?;
+
+        Ok(Json(table.state()))
+    })
+}
+
+/// Server-sent events with the changes of the game state. The first event is a snapshot
+/// of the full state, followed by a diff for every accepted action. Clients falling too
+/// far behind are disconnected and resync by reconnecting.
+#[get("/games/<game_id>/events")]
+fn events(
+    lobby: &State<Lobby>,
+    game_id: GameId,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![Event + 'static], ApiError> {
+    let (snapshot, mut updates) =
+        lobby.with_table(game_id, |table| Ok(table.feed().subscribe(table.state())))?;
+
+    Ok(EventStream! {
+        yield snapshot.to_event();
+
+        loop {
+            let update = select! {
+                update = updates.recv() => match update {
+                    Ok(update) => update,
+                    Err(RecvError::Closed | RecvError::Lagged(_)) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            yield update.to_event();
+        }
+    })
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![get_state, get_log, replay, post_action, events]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::{json, Value};
+
+    use crate::testing::wait_for_state;
+
+    // Creates a started two player game and returns its ID along with the seat tokens
+    fn start_game(client: &Client) -> (u64, Vec<String>) {
+        let game: Value = client
+            .post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "num_players": 2, "seed": 3 }).to_string())
+            .dispatch()
+            .into_json()
+            .unwrap();
+        let id = game["id"].as_u64().unwrap();
+
+        let tokens = [("alice", 0), ("bob", 1)]
+            .into_iter()
+            .map(|(name, faction)| {
+                let player: Value = client
+                    .post(format!("/games/{}/join", id))
+                    .header(ContentType::JSON)
+                    .body(json!({ "name": name, "faction": faction }).to_string())
+                    .dispatch()
+                    .into_json()
+                    .unwrap();
+                player["token"].as_str().unwrap().to_string()
+            })
+            .collect();
+        client.post(format!("/games/{}/start", id)).dispatch();
+
+        (id, tokens)
+    }
+
+    // A free hex of player 0's color
+    fn first_own_hex(client: &Client, id: u64) -> Value {
+        let state: Value = client
+            .get(format!("/games/{}/state", id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        let color = &state["state"]["factions"][0]["color"];
+
+        state["state"]["map"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| {
+                row.as_array()
+                    .unwrap()
+                    .iter()
+                    .enumerate()
+                    .map(move |(c, hex)| (r, c, hex))
+            })
+            .find(|(_, _, hex)| hex["terrain"]["Land"] == *color && hex["building"].is_null())
+            .map(|(r, c, _)| json!([r, c]))
+            .unwrap()
+    }
+
+    fn post_action(
+        client: &Client,
+        id: u64,
+        player_id: usize,
+        token: &str,
+        action: Value,
+    ) -> (Status, Value) {
+        let res = client
+            .post(format!("/games/{}/actions", id))
+            .header(ContentType::JSON)
+            .body(json!({ "player_id": player_id, "token": token, "action": action }).to_string())
+            .dispatch();
+
+        (res.status(), res.into_json().unwrap())
+    }
+
+    #[test]
+    fn state_of_started_game() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let (id, _) = start_game(&client);
+
+        let res = client.get(format!("/games/{}/state", id)).dispatch();
+
+        assert_eq!(res.status(), Status::Ok);
+        let state: Value = res.into_json().unwrap();
+        assert_eq!(state["phase"], "BuildingPlacement");
+        assert_eq!(state["player_to_move"], 0);
+        assert_eq!(state["state"]["factions"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn state_of_open_game_is_rejected() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let game: Value = client
+            .post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "num_players": 2 }).to_string())
+            .dispatch()
+            .into_json()
+            .unwrap();
+
+        let res = client
+            .get(format!("/games/{}/state", game["id"]))
+            .dispatch();
+
+        assert_eq!(res.status(), Status::Conflict);
+    }
+
+    #[test]
+    fn rejected_action_is_explained() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let (id, tokens) = start_game(&client);
+
+        let (status, err) = post_action(
+            &client,
+            id,
+            1,
+            &tokens[1],
+            json!({ "type": "Place", "pos": [1, 0] }),
+        );
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(err["error"], "NotPlayersTurn");
+        assert_eq!(err["player_id"], 1);
+    }
+
+    #[test]
+    fn action_with_wrong_token_is_forbidden() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let (id, tokens) = start_game(&client);
+        let pos = first_own_hex(&client, id);
+
+        // Player 1 tries to place the first workshop of player 0
+        let (status, err) = post_action(
+            &client,
+            id,
+            0,
+            &tokens[1],
+            json!({ "type": "Place", "pos": pos }),
+        );
+
+        assert_eq!(status, Status::Forbidden);
+        assert_eq!(err, json!({ "error": "InvalidToken", "player_id": 0 }));
+        let log: Value = client
+            .get(format!("/games/{}/log", id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        assert!(log["entries"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn action_of_other_phase_is_rejected() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let (id, tokens) = start_game(&client);
+
+        let (status, err) = post_action(
+            &client,
+            id,
+            0,
+            &tokens[0],
+            json!({ "type": "Pass", "bonus_tile": null }),
+        );
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(err["error"], "ActionNotAllowed");
+    }
+
+    #[test]
+    fn accepted_actions_are_logged_and_replayable() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let (id, tokens) = start_game(&client);
+        let initial: Value = client
+            .get(format!("/games/{}/state", id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        let pos = first_own_hex(&client, id);
+
+        let (status, _) = post_action(
+            &client,
+            id,
+            0,
+            &tokens[0],
+            json!({ "type": "Place", "pos": pos }),
+        );
+        assert_eq!(status, Status::Ok);
+        post_action(
+            &client,
+            id,
+            1,
+            &tokens[1],
+            json!({ "type": "Pass", "bonus_tile": null }),
+        );
+
+        let log: Value = client
+            .get(format!("/games/{}/log", id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        assert_eq!(log["setup"]["seed"], 3);
+        assert_eq!(log["entries"].as_array().unwrap().len(), 1);
+        let replayed: Value = client
+            .get(format!("/games/{}/replay/0", id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        assert_eq!(replayed, initial);
+    }
+
+    #[test]
+    fn bot_acts_after_human_player() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let game: Value = client
+            .post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "num_players": 2, "seed": 3 }).to_string())
+            .dispatch()
+            .into_json()
+            .unwrap();
+        let id = game["id"].as_u64().unwrap();
+        let human: Value = client
+            .post(format!("/games/{}/join", id))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "alice", "faction": 0 }).to_string())
+            .dispatch()
+            .into_json()
+            .unwrap();
+        client
+            .post(format!("/games/{}/join", id))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "bot", "faction": 1, "bot": "Greedy" }).to_string())
+            .dispatch();
+        client.post(format!("/games/{}/start", id)).dispatch();
+
+        let (status, _) = post_action(
+            &client,
+            id,
+            0,
+            human["token"].as_str().unwrap(),
+            json!({ "type": "Place", "pos": first_own_hex(&client, id) }),
+        );
+        assert_eq!(status, Status::Ok);
+
+        // The bot places both its workshops, after which it is the human's turn again
+        wait_for_state(&client, id, |state| state["player_to_move"] == 0);
+        let log: Value = client
+            .get(format!("/games/{}/log", id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        assert_eq!(log["entries"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn replay_beyond_log_is_not_found() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let (id, _) = start_game(&client);
+
+        let res = client.get(format!("/games/{}/replay/1", id)).dispatch();
+
+        assert_eq!(res.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn events_of_unknown_game_are_not_found() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+
+        let res = client.get("/games/17/events").dispatch();
+
+        assert_eq!(res.status(), Status::NotFound);
+    }
+}
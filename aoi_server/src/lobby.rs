@@ -0,0 +1,534 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aoi_backend::actionlog::{GameSetup, LoggedGame};
+use aoi_backend::bonustile::BonusTile;
+use aoi_backend::bot::mcts::Budget;
+use aoi_backend::bot::{self, BotKind};
+use aoi_backend::common::Color;
+use aoi_backend::gamephase::PlayerId;
+use aoi_backend::pregame::{FactionPool, FactionSelector, PreGame};
+use aoi_backend::race::Race;
+use rocket::serde::json::Json;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::{Route, State};
+
+use crate::error::ApiError;
+use crate::table::{self, SharedTable, Table};
+
+pub type GameId = u32;
+
+pub const MIN_PLAYERS: u32 = 2;
+pub const MAX_PLAYERS: u32 = 5;
+
+/// All games hosted by the server, open for joining or already started.
+pub struct Lobby {
+    games: Mutex<Games>,
+}
+
+#[derive(Default)]
+struct Games {
+    next_id: GameId,
+    games: BTreeMap<GameId, LobbyGame>,
+}
+
+struct LobbyGame {
+    seed: u64,
+    num_players: u32,
+    faction_pool: FactionPool,
+    seats: Vec<Seat>, // Indices map to player IDs
+    stage: Stage,
+}
+
+struct Seat {
+    name: String,
+    bot: Option<BotKind>, // Seats without a bot are played by a human
+    token: String,        // Handed to the joining player to act for the seat
+}
+
+enum Stage {
+    Open { selector: FactionSelector },
+    Started { table: SharedTable },
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Lobby {
+            games: Mutex::new(Games::default()),
+        }
+    }
+
+    /// Runs `f` on the table of a game that has started, on behalf of the player holding
+    /// the token of the seat. Bots to act afterwards play on their own.
+    pub fn with_seat<T>(
+        &self,
+        game_id: GameId,
+        player_id: PlayerId,
+        token: &str,
+        f: impl FnOnce(&mut Table) -> Result<T, ApiError>,
+    ) -> Result<T, ApiError> {
+        let authorized = {
+            let games = self.games.lock().unwrap();
+            let game = games
+                .games
+                .get(&game_id)
+                .ok_or(ApiError::GameNotFound { game_id })?;
+            game.seats
+                .get(player_id)
+                .is_some_and(|seat| seat.token == token)
+        };
+        if !authorized {
+            return Err(ApiError::InvalidToken { player_id });
+        }
+
+        let table = self.table(game_id)?;
+        let res = f(&mut table.lock().unwrap());
+        table::spawn_bots(&table);
+
+        res
+    }
+
+    /// Runs `f` on the table of a game that has started.
+    pub fn with_table<T>(
+        &self,
+        game_id: GameId,
+        f: impl FnOnce(&mut Table) -> Result<T, ApiError>,
+    ) -> Result<T, ApiError> {
+        let table = self.table(game_id)?;
+        let res = f(&mut table.lock().unwrap()); // The guard must go before the table
+
+        res
+    }
+
+    // The lobby is only locked for finding the table, so that games don't wait for each other
+    fn table(&self, game_id: GameId) -> Result<SharedTable, ApiError> {
+        let games = self.games.lock().unwrap();
+        let game = games
+            .games
+            .get(&game_id)
+            .ok_or(ApiError::GameNotFound { game_id })?;
+
+        match &game.stage {
+            Stage::Open { .. } => Err(ApiError::GameNotStarted { game_id }),
+            Stage::Started { table } => Ok(table.clone()),
+        }
+    }
+}
+
+/// Settings of the bots seated by the server, read from the Rocket config, e.g. from
+/// `ROCKET_MCTS_ITERATIONS` or `ROCKET_MCTS_MILLIS`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BotConfig {
+    mcts_iterations: Option<u32>,
+    mcts_millis: Option<u64>, // Thinking time per action, preferred over the iterations
+}
+
+impl BotConfig {
+    pub fn mcts_budget(&self) -> Budget {
+        match (self.mcts_millis, self.mcts_iterations) {
+            (Some(millis), _) => Budget::Time(Duration::from_millis(millis)),
+            (None, Some(iterations)) => Budget::Iterations(iterations),
+            (None, None) => bot::DEFAULT_MCTS_BUDGET,
+        }
+    }
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LobbyGame {
+    fn new(pregame: PreGame) -> Self {
+        let selector = FactionSelector::new(&pregame);
+
+        LobbyGame {
+            seed: pregame.seed(),
+            num_players: pregame.num_players(),
+            faction_pool: pregame.faction_pool().clone(),
+            seats: Vec::new(),
+            stage: Stage::Open { selector },
+        }
+    }
+
+    fn is_started(&self) -> bool {
+        matches!(self.stage, Stage::Started { .. })
+    }
+
+    fn info(&self, id: GameId) -> GameInfo {
+        let selected: &[usize] = match &self.stage {
+            Stage::Open { selector, .. } => selector.selected(),
+            Stage::Started { .. } => &[],
+        };
+
+        GameInfo {
+            id,
+            seed: self.seed,
+            num_players: self.num_players,
+            started: self.is_started(),
+            player_to_move: match &self.stage {
+                Stage::Open { .. } => None,
+                Stage::Started { table } => table.lock().unwrap().game().game().player_to_move(),
+            },
+            players: self
+                .seats
+                .iter()
+                .enumerate()
+                .map(|(player_id, seat)| PlayerInfo {
+                    player_id,
+                    name: seat.name.clone(),
+                    bot: seat.bot,
+                    faction: selected.get(player_id).copied(),
+                })
+                .collect(),
+            faction_pool: self
+                .faction_pool
+                .iter()
+                .map(|&(race, bonus_tile, color)| FactionOption {
+                    race,
+                    bonus_tile,
+                    color,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CreateGame {
+    num_players: u32,
+    seed: Option<u64>, // A random seed is drawn if none is given
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JoinGame {
+    name: String,
+    faction: usize, // Index in the faction pool
+    #[serde(default)]
+    bot: Option<BotKind>, // Fills the seat with a bot instead of the joining player
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GameInfo {
+    id: GameId,
+    seed: u64,
+    num_players: u32,
+    started: bool,
+    player_to_move: Option<PlayerId>,
+    players: Vec<PlayerInfo>,
+    faction_pool: Vec<FactionOption>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PlayerInfo {
+    player_id: PlayerId,
+    name: String,
+    bot: Option<BotKind>,
+    faction: Option<usize>, // Not reported once the game has started
+}
+
+/// The seat taken by a joining player, along with the token needed to act for it.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct JoinedGame {
+    #[serde(flatten)]
+    player: PlayerInfo,
+    token: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FactionOption {
+    race: Race,
+    bonus_tile: BonusTile,
+    color: Color,
+}
+
+#[post("/games", data = "<req>")]
+fn create_game(lobby: &State<Lobby>, req: Json<CreateGame>) -> Result<Json<GameInfo>, ApiError> {
+    if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&req.num_players) {
+        return Err(ApiError::InvalidPlayerCount {
+            num_players: req.num_players,
+        });
+    }
+
+    let pregame = match req.seed {
+        Some(seed) => PreGame::new_seeded(req.num_players, seed),
+        None => PreGame::new_random(req.num_players),
+    };
+
+    let mut games = lobby.games.lock().unwrap();
+    let id = games.next_id;
+    games.next_id += 1;
+    let game = LobbyGame::new(pregame);
+    let info = game.info(id);
+    games.games.insert(id, game);
+
+    Ok(Json(info))
+}
+
+/// Games that are still waiting for players.
+#[get("/games")]
+fn list_games(lobby: &State<Lobby>) -> Json<Vec<GameInfo>> {
+    let games = lobby.games.lock().unwrap();
+
+    Json(
+        games
+            .games
+            .iter()
+            .filter(|(_, game)| !game.is_started())
+            .map(|(&id, game)| game.info(id))
+            .collect(),
+    )
+}
+
+#[get("/games/<game_id>")]
+fn get_game(lobby: &State<Lobby>, game_id: GameId) -> Result<Json<GameInfo>, ApiError> {
+    let games = lobby.games.lock().unwrap();
+    let game = games
+        .games
+        .get(&game_id)
+        .ok_or(ApiError::GameNotFound { game_id })?;
+
+    Ok(Json(game.info(game_id)))
+}
+
+/// Takes the next free seat by selecting one of the factions in the pool. Seats are
+/// handed out in the order the players join, and may be taken by bots. The returned
+/// token must be sent along with the actions of the seat.
+#[post("/games/<game_id>/join", data = "<req>")]
+fn join_game(
+    lobby: &State<Lobby>,
+    game_id: GameId,
+    req: Json<JoinGame>,
+) -> Result<Json<JoinedGame>, ApiError> {
+    let mut games = lobby.games.lock().unwrap();
+    let game = games
+        .games
+        .get_mut(&game_id)
+        .ok_or(ApiError::GameNotFound { game_id })?;
+
+    let Stage::Open { selector, .. } = &mut game.stage else {
+        return Err(ApiError::GameAlreadyStarted { game_id });
+    };
+    selector.select(req.faction)?;
+
+    let player_id = game.seats.len();
+    let token = format!("{:032x}", rand::random::<u128>());
+    game.seats.push(Seat {
+        name: req.name.clone(),
+        bot: req.bot,
+        token: token.clone(),
+    });
+
+    Ok(Json(JoinedGame {
+        player: PlayerInfo {
+            player_id,
+            name: req.name.clone(),
+            bot: req.bot,
+            faction: Some(req.faction),
+        },
+        token,
+    }))
+}
+
+/// Sets up the board once all seats are taken, after which the initial buildings are
+/// placed. Bots start acting in the background if the game is waiting for them.
+#[post("/games/<game_id>/start")]
+fn start_game(
+    lobby: &State<Lobby>,
+    config: &State<BotConfig>,
+    game_id: GameId,
+) -> Result<Json<GameInfo>, ApiError> {
+    let mut games = lobby.games.lock().unwrap();
+    let game = games
+        .games
+        .get_mut(&game_id)
+        .ok_or(ApiError::GameNotFound { game_id })?;
+
+    let Stage::Open { selector } = &game.stage else {
+        return Err(ApiError::GameAlreadyStarted { game_id });
+    };
+    selector.finish()?; // All seats must be taken
+
+    let setup = GameSetup {
+        seed: game.seed,
+        num_players: game.num_players,
+        factions: selector.selected().clone(),
+    };
+    // Bots draw their choices from the seed of the game, so seeded games replay alike
+    let bots = game
+        .seats
+        .iter()
+        .enumerate()
+        .filter_map(|(player_id, seat)| {
+            let seed = game.seed.wrapping_add(player_id as u64);
+            let bot = seat.bot?.create(seed, config.mcts_budget());
+            Some((player_id, bot))
+        })
+        .collect();
+    let table = Arc::new(Mutex::new(Table::new(LoggedGame::new(setup)?, bots)));
+    table::spawn_bots(&table);
+    game.stage = Stage::Started { table };
+
+    Ok(Json(game.info(game_id)))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![create_game, list_games, get_game, join_game, start_game]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::{json, Value};
+
+    use super::*;
+    use crate::testing::wait_for_state;
+
+    fn create_game(client: &Client, num_players: u32) -> Value {
+        let res = client
+            .post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "num_players": num_players, "seed": 42 }).to_string())
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        res.into_json().unwrap()
+    }
+
+    fn join(client: &Client, game_id: u64, name: &str, faction: usize) -> (Status, Value) {
+        let res = client
+            .post(format!("/games/{}/join", game_id))
+            .header(ContentType::JSON)
+            .body(json!({ "name": name, "faction": faction }).to_string())
+            .dispatch();
+
+        (res.status(), res.into_json().unwrap())
+    }
+
+    #[test]
+    fn play_through_lobby() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let game = create_game(&client, 2);
+        let id = game["id"].as_u64().unwrap();
+        assert_eq!(game["seed"], 42);
+        assert_eq!(game["faction_pool"].as_array().unwrap().len(), 7);
+
+        let (status, player) = join(&client, id, "alice", 3);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(player["player_id"], 0);
+        let (_, player) = join(&client, id, "bob", 5);
+        assert_eq!(player["player_id"], 1);
+        assert_eq!(player["token"].as_str().unwrap().len(), 32);
+
+        let res = client.post(format!("/games/{}/start", id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let game: Value = res.into_json().unwrap();
+        assert_eq!(game["started"], true);
+        assert_eq!(game["player_to_move"], 0);
+
+        assert!(game["players"][1]["token"].is_null()); // Only told to the joining player
+        let open: Value = client.get("/games").dispatch().into_json().unwrap();
+        assert!(open.as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn bots_play_their_seats() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let id = create_game(&client, 2)["id"].as_u64().unwrap();
+
+        for (name, faction, bot) in [("rand", 0, "Random"), ("greed", 1, "Greedy")] {
+            let res = client
+                .post(format!("/games/{}/join", id))
+                .header(ContentType::JSON)
+                .body(json!({ "name": name, "faction": faction, "bot": bot }).to_string())
+                .dispatch();
+            let player: Value = res.into_json().unwrap();
+            assert_eq!(player["bot"], bot);
+        }
+        let res = client.post(format!("/games/{}/start", id)).dispatch();
+        assert_eq!(res.status(), Status::Ok);
+
+        // No human is seated, so the bots play the whole game on their own
+        let state = wait_for_state(&client, id, |state| state["phase"] == "FinalScoring");
+        assert_eq!(state["player_to_move"], Value::Null);
+    }
+
+    #[test]
+    fn cant_start_before_all_seats_are_taken() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let id = create_game(&client, 3)["id"].as_u64().unwrap();
+        join(&client, id, "alice", 0);
+
+        let res = client.post(format!("/games/{}/start", id)).dispatch();
+
+        assert_eq!(res.status(), Status::BadRequest);
+        assert_eq!(
+            res.into_json::<Value>().unwrap(),
+            json!({ "error": "FactionSelectionNotFinished" })
+        );
+    }
+
+    #[test]
+    fn cant_join_with_taken_faction() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+        let id = create_game(&client, 2)["id"].as_u64().unwrap();
+        join(&client, id, "alice", 0);
+
+        let (status, err) = join(&client, id, "bob", 0);
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(err, json!({ "error": "FactionAlreadySelected", "idx": 0 }));
+    }
+
+    #[test]
+    fn unknown_game_is_not_found() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+
+        let res = client.get("/games/17").dispatch();
+
+        assert_eq!(res.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn mcts_budget_is_configurable() {
+        let config = |key, value| {
+            rocket::Config::figment()
+                .merge((key, value))
+                .extract::<BotConfig>()
+                .unwrap()
+        };
+
+        assert_eq!(BotConfig::default().mcts_budget(), bot::DEFAULT_MCTS_BUDGET);
+        assert_eq!(
+            config("mcts_iterations", 50).mcts_budget(),
+            Budget::Iterations(50)
+        );
+        assert_eq!(
+            config("mcts_millis", 20).mcts_budget(),
+            Budget::Time(Duration::from_millis(20))
+        );
+    }
+
+    #[test]
+    fn player_count_is_validated() {
+        let client = Client::tracked(crate::rocket()).unwrap();
+
+        let res = client
+            .post("/games")
+            .header(ContentType::JSON)
+            .body(json!({ "num_players": 6 }).to_string())
+            .dispatch();
+
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+}
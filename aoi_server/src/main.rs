@@ -1,8 +1,19 @@
 use aoi_backend::map;
+use rocket::fairing::AdHoc;
 
 #[macro_use]
 extern crate rocket;
 
+mod error;
+mod feed;
+mod table;
+#[cfg(test)]
+mod testing;
+
+// Public so that the `uri!` macros Rocket generates for their routes are exported
+pub mod game;
+pub mod lobby;
+
 #[get("/")]
 fn hello() -> &'static str {
     let _map = map::open_map(map::MapId::Base);
@@ -12,5 +23,10 @@ fn hello() -> &'static str {
 
 #[launch]
 fn rocket() -> _ {
-    rocket::build().mount("/", routes![hello])
+    rocket::build()
+        .manage(lobby::Lobby::new())
+        .attach(AdHoc::config::<lobby::BotConfig>())
+        .mount("/", routes![hello])
+        .mount("/", lobby::routes())
+        .mount("/", game::routes())
 }
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use aoi_backend::actionlog::LoggedGame;
+use aoi_backend::bot::Bot;
+use aoi_backend::game::Action;
+use aoi_backend::gamephase::PlayerId;
+use aoi_backend::movegen;
+use rocket::serde::json::{json, Value};
+
+use crate::error::ApiError;
+use crate::feed::Feed;
+
+/// A started game along with the feed of its updates and the bots sitting at it.
+pub struct Table {
+    game: LoggedGame,
+    feed: Feed,
+    bots: BTreeMap<PlayerId, Box<dyn Bot + Send>>, // Taken out while the bot thinks
+    bots_playing: bool, // Whether a worker is playing the turns of the bots
+}
+
+/// A table shared by the requests of its players and the worker playing its bots.
+pub type SharedTable = Arc<Mutex<Table>>;
+
+impl Table {
+    /// Seats the bots, which start acting once the table is handed to `spawn_bots`.
+    pub fn new(game: LoggedGame, bots: BTreeMap<PlayerId, Box<dyn Bot + Send>>) -> Self {
+        Table {
+            game,
+            feed: Feed::new(),
+            bots,
+            bots_playing: false,
+        }
+    }
+
+    pub fn game(&self) -> &LoggedGame {
+        &self.game
+    }
+
+    pub fn feed(&self) -> &Feed {
+        &self.feed
+    }
+
+    /// Applies the action of a player and publishes the change. Bots to act next are
+    /// left to `spawn_bots`.
+    pub fn apply(&mut self, player_id: PlayerId, action: Action) -> Result<(), ApiError> {
+        self.publish_action(player_id, action)
+    }
+
+    pub fn state(&self) -> Value {
+        json!(self.game.game())
+    }
+
+    fn publish_action(&mut self, player_id: PlayerId, action: Action) -> Result<(), ApiError> {
+        let old = self.state();
+        self.game.apply(player_id, action)?;
+        self.feed.publish(&old, &self.state());
+
+        Ok(())
+    }
+
+    // The bot the game is waiting for, if any
+    fn bot_to_act(&self) -> Option<PlayerId> {
+        movegen::player_to_act(self.game.game())
+            .filter(|player_id| self.bots.contains_key(player_id))
+    }
+
+    // Bots must choose a legal action while it is their turn. Should one fail to, the first
+    // legal action is taken instead so that the game can't get stuck.
+    fn apply_bot_action(&mut self, player_id: PlayerId, chosen: Option<Action>) {
+        let err = match chosen.clone() {
+            Some(action) => match self.publish_action(player_id, action) {
+                Ok(()) => return,
+                Err(err) => Some(err),
+            },
+            None => None,
+        };
+
+        error!(
+            "Bot of player {} chose {:?} (error: {:?})",
+            player_id, chosen, err
+        );
+        let fallback = movegen::legal_actions(self.game.game())
+            .into_iter()
+            .next()
+            .expect("The player to act has a legal action");
+        self.publish_action(player_id, fallback)
+            .expect("Legal actions are accepted");
+    }
+}
+
+/// Lets the bots act on a blocking worker if the game is waiting for one of them, so that
+/// requests return without waiting for the bots to think.
+pub fn spawn_bots(table: &SharedTable) {
+    {
+        let mut table = table.lock().unwrap();
+        if table.bots_playing || table.bot_to_act().is_none() {
+            return;
+        }
+        table.bots_playing = true;
+    }
+
+    let table = table.clone();
+    rocket::tokio::task::spawn_blocking(move || play_bots(&table));
+}
+
+// Plays the turns of the bots until the game waits for a human player or is over. The
+// table is only locked to hand the game to a bot and to apply its choice, never while the
+// bot thinks.
+fn play_bots(table: &SharedTable) {
+    loop {
+        let (player_id, mut bot, game, num_entries) = {
+            let mut table = table.lock().unwrap();
+            let Some(player_id) = table.bot_to_act() else {
+                table.bots_playing = false;
+                return;
+            };
+            let bot = table.bots.remove(&player_id).expect("Bot is seated");
+            let game = table.game.game().clone();
+
+            (player_id, bot, game, table.game.log().entries().len())
+        };
+
+        let chosen = bot.choose(&game);
+
+        let mut table = table.lock().unwrap();
+        table.bots.insert(player_id, bot);
+        // Choices made for a game that has moved on in the meantime are thought over
+        if table.game.log().entries().len() == num_entries {
+            table.apply_bot_action(player_id, chosen);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::Duration;
+
+    use aoi_backend::actionlog::GameSetup;
+    use aoi_backend::bot::random::RandomBot;
+    use aoi_backend::game::Game;
+
+    // Keeps trying an action that is never legal
+    struct BrokenBot;
+
+    impl Bot for BrokenBot {
+        fn choose(&mut self, _game: &Game) -> Option<Action> {
+            Some(Action::DeclineSpades)
+        }
+    }
+
+    fn shared_table(bots: Vec<Box<dyn Bot + Send>>) -> SharedTable {
+        let setup = GameSetup {
+            seed: 3,
+            num_players: 2,
+            factions: vec![0, 1],
+        };
+        let bots = bots.into_iter().enumerate().collect();
+
+        Arc::new(Mutex::new(Table::new(
+            LoggedGame::new(setup).unwrap(),
+            bots,
+        )))
+    }
+
+    #[test]
+    fn illegal_bot_action_is_replaced() {
+        let table = shared_table(vec![Box::new(BrokenBot), Box::new(BrokenBot)]);
+
+        play_bots(&table);
+
+        let table = table.lock().unwrap();
+        assert!(table.game().game().is_finished());
+        assert!(!table.bots_playing);
+    }
+
+    // Waits for the test to let it go on before every choice, until the test is done
+    struct WaitingBot {
+        go: Receiver<()>,
+        bot: RandomBot,
+    }
+
+    impl Bot for WaitingBot {
+        fn choose(&mut self, game: &Game) -> Option<Action> {
+            let _ = self.go.recv();
+            self.bot.choose(game)
+        }
+    }
+
+    #[test]
+    fn table_is_not_locked_while_bot_thinks() {
+        let (go, wait) = mpsc::channel();
+        let bot = WaitingBot {
+            go: wait,
+            bot: RandomBot::new(0),
+        };
+        let table = shared_table(vec![Box::new(bot), Box::new(RandomBot::new(1))]);
+
+        let worker = {
+            let table = table.clone();
+            thread::spawn(move || play_bots(&table))
+        };
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(table.lock().unwrap().game().log().entries().is_empty());
+        go.send(()).unwrap();
+        drop(go);
+        worker.join().unwrap();
+        assert!(table.lock().unwrap().game().game().is_finished());
+    }
+}
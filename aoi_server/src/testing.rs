@@ -0,0 +1,27 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rocket::local::blocking::Client;
+use rocket::serde::json::Value;
+
+// Bots taking longer than this for a whole game fail the test
+const BOT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Polls the state of a started game until it meets `done`, since bots act in the
+/// background. Returns the state meeting it.
+pub fn wait_for_state(client: &Client, game_id: u64, done: impl Fn(&Value) -> bool) -> Value {
+    let start = Instant::now();
+    loop {
+        let state: Value = client
+            .get(format!("/games/{}/state", game_id))
+            .dispatch()
+            .into_json()
+            .unwrap();
+        if done(&state) {
+            return state;
+        }
+
+        assert!(start.elapsed() < BOT_TIMEOUT, "Bots did not act in time");
+        thread::sleep(Duration::from_millis(10));
+    }
+}